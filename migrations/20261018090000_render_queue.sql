@@ -0,0 +1,20 @@
+-- Add migration script here
+CREATE TABLE "render_job" (
+  "id" INTEGER PRIMARY KEY,
+  "status" TEXT NOT NULL DEFAULT 'queued',
+  "kind" TEXT NOT NULL,
+  "reference" TEXT NOT NULL,
+  "map_id" INTEGER NOT NULL,
+  "title" TEXT NOT NULL,
+  "subtitle" TEXT DEFAULT NULL,
+  "skin_identifier" TEXT DEFAULT NULL,
+  "skip_beatmap_download" BOOLEAN NOT NULL DEFAULT 0,
+  "channel_id" INTEGER NOT NULL,
+  "message_id" INTEGER NOT NULL,
+  "created_at" INTEGER NOT NULL,
+  "started_at" INTEGER DEFAULT NULL,
+  "finished_at" INTEGER DEFAULT NULL,
+  "video_path" TEXT DEFAULT NULL
+);
+
+CREATE INDEX "render_job_status" ON "render_job" ("status", "id");
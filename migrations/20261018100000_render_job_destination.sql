@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE "render_job" ADD COLUMN "destination" TEXT NOT NULL DEFAULT 'youtube';
@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE "render_job" ADD COLUMN "privacy" TEXT DEFAULT NULL;
+ALTER TABLE "render_job" ADD COLUMN "publish_at" INTEGER DEFAULT NULL;
+ALTER TABLE "render_job" ADD COLUMN "playlists" TEXT DEFAULT NULL;
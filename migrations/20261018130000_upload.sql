@@ -0,0 +1,22 @@
+-- Add migration script here
+ALTER TABLE "render_job" ADD COLUMN "requested_by" INTEGER DEFAULT NULL;
+ALTER TABLE "render_job" ADD COLUMN "approved_by" INTEGER DEFAULT NULL;
+
+CREATE TABLE "upload" (
+  "id" INTEGER PRIMARY KEY,
+  "kind" TEXT NOT NULL,
+  "reference" TEXT NOT NULL,
+  "map_id" INTEGER NOT NULL,
+  "osu_user_id" INTEGER NOT NULL,
+  "requested_by" INTEGER DEFAULT NULL,
+  "approved_by" INTEGER DEFAULT NULL,
+  "skin" TEXT DEFAULT NULL,
+  "destination" TEXT NOT NULL,
+  "video_id" TEXT DEFAULT NULL,
+  "title" TEXT DEFAULT NULL,
+  "description" TEXT DEFAULT NULL,
+  "requested_at" INTEGER NOT NULL,
+  "uploaded_at" INTEGER NOT NULL
+);
+
+CREATE INDEX "upload_reference" ON "upload" ("reference");
@@ -0,0 +1,22 @@
+-- Add migration script here
+CREATE TABLE "suggestion" (
+  "id" INTEGER PRIMARY KEY,
+  "status" TEXT NOT NULL DEFAULT 'pending',
+  "kind" TEXT NOT NULL,
+  "reference" TEXT NOT NULL,
+  "map_id" INTEGER NOT NULL,
+  "osu_user_id" INTEGER NOT NULL,
+  "mode" INTEGER NOT NULL,
+  "requested_by" INTEGER NOT NULL,
+  "reason" TEXT DEFAULT NULL,
+  "skin_identifier" TEXT DEFAULT NULL,
+  "channel_id" INTEGER DEFAULT NULL,
+  "message_id" INTEGER DEFAULT NULL,
+  "handled_by" INTEGER DEFAULT NULL,
+  "created_at" INTEGER NOT NULL,
+  "handled_at" INTEGER DEFAULT NULL
+);
+
+CREATE INDEX "suggestion_status" ON "suggestion" ("status", "id");
+
+ALTER TABLE "render_job" ADD COLUMN "suggestion_id" INTEGER DEFAULT NULL;
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE "suggestion" ADD COLUMN "decline_reason" TEXT DEFAULT NULL;
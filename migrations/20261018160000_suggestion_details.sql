@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE "suggestion" ADD COLUMN "player_name" TEXT DEFAULT NULL;
+ALTER TABLE "suggestion" ADD COLUMN "map_title" TEXT DEFAULT NULL;
+ALTER TABLE "suggestion" ADD COLUMN "pp" REAL DEFAULT NULL;
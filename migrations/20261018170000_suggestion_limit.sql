@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE "suggestion_limit" (
+  "id" INTEGER PRIMARY KEY NOT NULL,
+  "max_pending" INTEGER,
+  "cooldown_seconds" INTEGER,
+  "daily_cap" INTEGER
+);
+
+INSERT INTO "suggestion_limit" ("id", "max_pending", "cooldown_seconds", "daily_cap") VALUES (1, NULL, NULL, NULL);
+
+CREATE INDEX "suggestion_requested_by" ON "suggestion" ("requested_by", "created_at");
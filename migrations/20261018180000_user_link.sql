@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE "user" ADD COLUMN "osu_username" TEXT;
+ALTER TABLE "user" ADD COLUMN "linked_at" INTEGER;
+
+CREATE INDEX "user_osu_id" ON "user" ("osu_id");
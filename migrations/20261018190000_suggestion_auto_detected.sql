@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE "suggestion" ADD COLUMN "auto_detected" BOOLEAN NOT NULL DEFAULT 0;
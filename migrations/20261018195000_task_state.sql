@@ -0,0 +1,5 @@
+-- Add migration script here
+CREATE TABLE "task_state" (
+  "key" TEXT PRIMARY KEY NOT NULL,
+  "value" INTEGER NOT NULL
+);
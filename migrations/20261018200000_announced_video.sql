@@ -0,0 +1,9 @@
+-- Add migration script here
+-- Announced videos are kept per feed subscription. The feed configured through
+-- the environment uses subscription 0.
+CREATE TABLE "announced_video" (
+  "subscription" INTEGER NOT NULL DEFAULT 0,
+  "video_id" TEXT NOT NULL,
+  "announced_at" INTEGER NOT NULL,
+  PRIMARY KEY ("subscription", "video_id")
+);
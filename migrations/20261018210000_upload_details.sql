@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE "upload" ADD COLUMN "mods" TEXT DEFAULT NULL;
+ALTER TABLE "upload" ADD COLUMN "pp" REAL DEFAULT NULL;
+
+CREATE INDEX "upload_video_id" ON "upload" ("video_id");
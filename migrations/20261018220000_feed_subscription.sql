@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE "feed_subscription" (
+  "id" INTEGER PRIMARY KEY NOT NULL,
+  "youtube_channel_id" TEXT NOT NULL,
+  "discord_channel_id" INTEGER NOT NULL,
+  "template" TEXT DEFAULT NULL,
+  "role_id" INTEGER DEFAULT NULL,
+  "created_at" INTEGER NOT NULL,
+  UNIQUE ("youtube_channel_id", "discord_channel_id")
+);
@@ -0,0 +1,237 @@
+//! osu! account linking via the OAuth authorization code grant. `/link` hands out
+//! an authorize URL carrying a one-time state; osu! sends the browser back to a
+//! small callback listener, which exchanges the code and stores the link.
+
+use std::{collections::HashMap, io::Read, sync::{Arc, LazyLock, Mutex}, time::{Duration, Instant}};
+
+use poise::serenity_prelude::{self as serenity, CreateMessage};
+use rosu_v2::prelude as rosu;
+use tokio::{io::{AsyncBufReadExt, AsyncWriteExt, BufReader}, net::{TcpListener, TcpStream}};
+use url::Url;
+
+use crate::{Error, db, defaults::{LINK_LISTEN, LINK_REDIRECT_URI}, discord_helper::MessageState, embeds};
+
+const AUTHORIZE_URL: &str = "https://osu.ppy.sh/oauth/authorize";
+const STATE_LIFETIME: Duration = Duration::from_secs(10 * 60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct PendingLink {
+    user: serenity::UserId,
+    expires: Instant,
+}
+
+static PENDING: LazyLock<Mutex<HashMap<String, PendingLink>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, PartialEq)]
+enum Callback {
+    Code { code: String, state: String },
+    Denied { state: String },
+}
+
+pub fn is_enabled() -> bool {
+    LINK_REDIRECT_URI.is_some()
+}
+
+fn client_id() -> u64 {
+    std::env::var("OSC_BOT_CLIENT_ID")
+        .expect("Client id must be defined")
+        .parse()
+        .expect("CLient id must be integer")
+}
+
+fn new_state() -> Result<String, Error> {
+    let mut bytes = [0u8; 16];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// The osu! authorize URL for `user`. Any earlier, unfinished `/link` of the same
+/// user stops working.
+pub fn authorize_url(user: serenity::UserId) -> Result<String, Error> {
+    let redirect_uri = LINK_REDIRECT_URI.as_ref().ok_or("OSC_BOT_OSU_REDIRECT_URI is not set")?;
+    let state = new_state()?;
+
+    let mut pending = PENDING.lock().unwrap();
+    let now = Instant::now();
+    pending.retain(|_, link| link.user != user && link.expires > now);
+    pending.insert(state.clone(), PendingLink { user, expires: now + STATE_LIFETIME });
+
+    let mut url = Url::parse(AUTHORIZE_URL)?;
+    url.query_pairs_mut()
+        .append_pair("client_id", &client_id().to_string())
+        .append_pair("redirect_uri", redirect_uri.as_str())
+        .append_pair("response_type", "code")
+        .append_pair("scope", "identify")
+        .append_pair("state", &state);
+    Ok(url.to_string())
+}
+
+fn take_pending(state: &str) -> Option<serenity::UserId> {
+    let link = PENDING.lock().unwrap().remove(state)?;
+    (link.expires > Instant::now()).then_some(link.user)
+}
+
+/// Starts the callback listener if a redirect URI is configured.
+pub fn start_listener(http: Arc<serenity::Http>) {
+    if !is_enabled() {
+        return;
+    }
+    tokio::spawn(async move {
+        let listener = match TcpListener::bind(LINK_LISTEN.as_str()).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::error!(error = %e, address = %*LINK_LISTEN, "Could not start the osu! link callback listener");
+                return;
+            }
+        };
+        tracing::info!(address = %*LINK_LISTEN, "osu! link callback listening");
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Could not accept osu! link callback");
+                    continue;
+                }
+            };
+            let http = http.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, http).await {
+                    tracing::warn!(error = %e, "osu! link callback failed");
+                }
+            });
+        }
+    });
+}
+
+async fn handle_connection(mut stream: TcpStream, http: Arc<serenity::Http>) -> Result<(), Error> {
+    let mut request_line = String::new();
+    tokio::time::timeout(REQUEST_TIMEOUT, BufReader::new(&mut stream).read_line(&mut request_line)).await??;
+
+    let callback_path = LINK_REDIRECT_URI.as_ref().map(Url::path).unwrap_or("/");
+    let (status, message) = match parse_callback(&request_line, callback_path) {
+        Some(Callback::Code { code, state }) => match complete_link(&code, &state, http).await {
+            Ok(message) => ("200 OK", message),
+            Err(message) => ("400 Bad Request", message),
+        },
+        Some(Callback::Denied { state }) => {
+            take_pending(&state);
+            ("200 OK", "Linking was cancelled. You can close this tab.".to_string())
+        },
+        None => ("404 Not Found", "Nothing here.".to_string()),
+    };
+
+    let body = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>osc bot</title></head><body><p>{}</p></body></html>",
+        escape_html(&message)
+    );
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Reads code and state from a `GET <callback_path>?...` request line.
+fn parse_callback(request_line: &str, callback_path: &str) -> Option<Callback> {
+    let mut parts = request_line.split_whitespace();
+    if parts.next()? != "GET" {
+        return None;
+    }
+    let url = Url::parse("http://localhost").ok()?.join(parts.next()?).ok()?;
+    if url.path() != callback_path {
+        return None;
+    }
+
+    let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    let state = query.get("state")?.clone();
+    match query.get("code") {
+        Some(code) => Some(Callback::Code { code: code.clone(), state }),
+        None => Some(Callback::Denied { state }),
+    }
+}
+
+/// Exchanges the code and links the account. Both sides are the text shown in the browser.
+async fn complete_link(code: &str, state: &str, http: Arc<serenity::Http>) -> Result<String, String> {
+    let Some(discord_user) = take_pending(state) else {
+        return Err("This link is invalid or has expired. Run /link again.".to_string());
+    };
+    let redirect_uri = LINK_REDIRECT_URI.as_ref().map(Url::to_string).unwrap_or_default();
+    let client_secret = std::env::var("OSC_BOT_CLIENT_SECRET").expect("Client secret must be defined");
+
+    let player = match rosu::Osu::builder()
+        .client_id(client_id())
+        .client_secret(client_secret)
+        .with_authorization(code, redirect_uri, rosu::Scopes::Identify)
+        .build()
+        .await
+    {
+        Ok(osu) => osu.own_data().await,
+        Err(e) => Err(e),
+    };
+    let player = match player {
+        Ok(player) => player,
+        Err(e) => {
+            tracing::warn!(error = %e, user = %discord_user, "Could not verify osu! account");
+            return Err("osu! didn't confirm your account. Run /link again.".to_string());
+        }
+    };
+
+    let discord_id = discord_user.get() as i64;
+    match db::get_linked_user_by_osu_id(player.user_id as i64).await {
+        Ok(Some(linked)) if linked.discord_id != discord_id => {
+            return Err(format!("{} is already linked to another Discord account.", player.username));
+        },
+        Ok(_) => (),
+        Err(e) => {
+            tracing::error!(error = %e, "Could not look up osu! link");
+            return Err("Something went wrong. Try again later.".to_string());
+        }
+    }
+    if let Err(e) = db::link_user(discord_id, player.user_id as i64, player.username.to_string()).await {
+        tracing::error!(error = %e, "Could not store osu! link");
+        return Err("Something went wrong. Try again later.".to_string());
+    }
+    tracing::info!(user = %discord_user, osu_id = player.user_id, "osu! account linked");
+
+    let embed = embeds::single_text_response_embed(
+        &format!("Your Discord account is now linked to [{}](https://osu.ppy.sh/users/{}).", player.username, player.user_id),
+        MessageState::SUCCESS,
+    );
+    if let Err(e) = discord_user.direct_message(&http, CreateMessage::default().embed(embed)).await {
+        tracing::warn!(error = %e, "Could not DM the osu! link confirmation");
+    }
+
+    Ok(format!("Linked to {}. You can close this tab.", player.username))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_code_and_state() {
+        assert_eq!(
+            parse_callback("GET /osu/callback?code=abc&state=f00 HTTP/1.1\r\n", "/osu/callback"),
+            Some(Callback::Code { code: "abc".to_string(), state: "f00".to_string() })
+        );
+        assert_eq!(
+            parse_callback("GET /osu/callback?error=access_denied&state=f00 HTTP/1.1\r\n", "/osu/callback"),
+            Some(Callback::Denied { state: "f00".to_string() })
+        );
+    }
+
+    #[test]
+    fn ignores_other_requests() {
+        assert_eq!(parse_callback("GET /favicon.ico HTTP/1.1\r\n", "/osu/callback"), None);
+        assert_eq!(parse_callback("POST /osu/callback?code=abc&state=f00 HTTP/1.1\r\n", "/osu/callback"), None);
+        assert_eq!(parse_callback("GET /osu/callback?code=abc HTTP/1.1\r\n", "/osu/callback"), None);
+    }
+}
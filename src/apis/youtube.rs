@@ -1,12 +1,22 @@
 use google_youtube3 as youtube;
+use poise::serenity_prelude::{self as serenity, CreateMessage};
+use serde::Deserialize;
 use tokio::time::{Instant, sleep};
+use std::env;
+use std::future::Future;
 use std::io::Cursor;
 use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
-use youtube::api::{Video, VideoSnippet, VideoStatus};
+use youtube::api::{PlaylistItem, PlaylistItemSnippet, ResourceId, Scope, Video, VideoSnippet, VideoStatus};
 use youtube::{hyper_rustls, hyper_util, yup_oauth2, YouTube};
+use yup_oauth2::authenticator::Authenticator;
+use yup_oauth2::authenticator_delegate::{DeviceAuthResponse, DeviceFlowDelegate};
+use yup_oauth2::storage::TokenInfo;
 
-use crate::Error;
+use crate::defaults::{YOUTUBE_ADMIN, YOUTUBE_PLAYLISTS, YOUTUBE_PRIVACY};
+use crate::{Error, embeds};
 
 pub async fn wait_open(path: &Path, timeout: Duration) -> std::io::Result<std::fs::File> {
     let end = Instant::now() + timeout;
@@ -22,60 +32,267 @@ pub async fn wait_open(path: &Path, timeout: Duration) -> std::io::Result<std::f
     }
 }
 
-// thank god for chatGPT
-pub async fn upload(video_path: &String, title: String, description: String, thumbnail: Vec<u8>) -> Result<String, Error> {
-    tracing::info!("Uploading to youtube...");
+type Connector = hyper_rustls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector>;
+
+// Always persist OAuth tokens into the project working directory.
+// This is intentionally not configurable to keep local + Docker behavior identical.
+const TOKEN_PATH: &str = "token.json";
+const SECRET_PATH: &str = "youtube_secret.json";
+
+// Editing videos and playlists needs the full youtube scope, which is also the
+// only one Google's device flow hands out. yup_oauth2 only reuses a stored token
+// for exactly the scopes it was granted, so installed-flow tokens from before
+// editing existed (youtube.upload) keep being used for uploads.
+const FULL_SCOPE: Scope = Scope::Full;
 
-    // Always persist OAuth tokens into the project working directory.
-    // This is intentionally not configurable to keep local + Docker behavior identical.
-    let token_path = "token.json";
+static DISCORD_HTTP: OnceLock<Arc<serenity::Http>> = OnceLock::new();
 
+/// Lets the device flow DM `OSC_BOT_YOUTUBE_ADMIN` when an upload finds no usable token.
+pub fn set_discord_http(http: Arc<serenity::Http>) {
+    DISCORD_HTTP.set(http).ok();
+}
+
+fn use_device_flow() -> bool {
+    env::var("OSC_BOT_YOUTUBE_AUTH").map(|flow| flow.eq_ignore_ascii_case("device")).unwrap_or(false)
+}
+
+fn connector() -> Connector {
+    hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .unwrap()
+        .https_or_http()
+        .enable_http1()
+        .build()
+}
+
+/// Sends the device-flow verification URL and user code to a Discord user.
+struct DiscordDeviceFlowDelegate {
+    http: Option<Arc<serenity::Http>>,
+    user: Option<serenity::UserId>,
+}
+
+impl DeviceFlowDelegate for DiscordDeviceFlowDelegate {
+    fn present_user_code<'a>(&'a self, device_auth_resp: &'a DeviceAuthResponse) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            tracing::warn!(
+                url = device_auth_resp.verification_uri,
+                code = device_auth_resp.user_code,
+                "YouTube authorization required"
+            );
+            let (Some(http), Some(user)) = (&self.http, self.user) else {
+                return;
+            };
+            let embed = embeds::youtube_device_code_embed(
+                &device_auth_resp.verification_uri,
+                &device_auth_resp.user_code,
+                device_auth_resp.expires_at.unix_timestamp(),
+            );
+            if let Err(e) = user.direct_message(http, CreateMessage::default().embed(embed)).await {
+                tracing::error!(error = %e, "Could not DM the YouTube authorization code");
+            }
+        })
+    }
+}
+
+async fn authenticator(delegate: DiscordDeviceFlowDelegate, token_path: &str, device_flow: bool) -> Result<Authenticator<Connector>, Error> {
     // Read OAuth client secret downloaded from Google Cloud Console
-    let secret = yup_oauth2::read_application_secret("youtube_secret.json").await?;
-
-    // Installed-app OAuth flow (opens browser / local redirect)
-    let auth = yup_oauth2::InstalledFlowAuthenticator::with_client(
-        secret,
-        yup_oauth2::InstalledFlowReturnMethod::HTTPRedirect,
-        yup_oauth2::client::CustomHyperClientBuilder::from(
-            hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
-                .build(
-                    hyper_rustls::HttpsConnectorBuilder::new()
-                        .with_native_roots()
-                        .unwrap()
-                        .https_or_http()
-                        .enable_http1()
-                        .build(),
-                ),
-        ),
-    )
-    .persist_tokens_to_disk(token_path)
-    .build()
-    .await?;
+    let secret = yup_oauth2::read_application_secret(SECRET_PATH).await?;
+    let client = yup_oauth2::client::CustomHyperClientBuilder::from(
+        hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new()).build(connector()),
+    );
+
+    let auth = if device_flow {
+        yup_oauth2::DeviceFlowAuthenticator::with_client(secret, client)
+            .flow_delegate(Box::new(delegate))
+            .persist_tokens_to_disk(token_path)
+            .build()
+            .await?
+    } else {
+        // Installed-app OAuth flow (opens browser / local redirect)
+        yup_oauth2::InstalledFlowAuthenticator::with_client(
+            secret,
+            yup_oauth2::InstalledFlowReturnMethod::HTTPRedirect,
+            client,
+        )
+        .persist_tokens_to_disk(token_path)
+        .build()
+        .await?
+    };
+    Ok(auth)
+}
+
+async fn hub() -> Result<YouTube<Connector>, Error> {
+    let delegate = DiscordDeviceFlowDelegate {
+        http: DISCORD_HTTP.get().cloned(),
+        user: *YOUTUBE_ADMIN,
+    };
+    let auth = authenticator(delegate, TOKEN_PATH, use_device_flow()).await?;
 
     // Hyper client (as in google-youtube3 README)
     let client = hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
-        .build(
-            hyper_rustls::HttpsConnectorBuilder::new()
-                .with_native_roots()
-                .unwrap()
-                .https_or_http()
-                .enable_http1()
-                .build(),
-        );
+        .build(connector());
+
+    Ok(YouTube::new(client, auth))
+}
+
+/// Run the device flow on behalf of `user`: they get the code by DM and the bot
+/// polls Google until they approve it. The token is written next to the current
+/// one and only replaces it once the flow succeeded.
+pub async fn authorize_with_device_flow(http: Arc<serenity::Http>, user: serenity::UserId) -> Result<(), Error> {
+    tracing::info!("Starting YouTube device authorization...");
+    let pending_path = format!("{}.pending", TOKEN_PATH);
+    tokio::fs::remove_file(&pending_path).await.ok();
+
+    let delegate = DiscordDeviceFlowDelegate { http: Some(http), user: Some(user) };
+    let auth = authenticator(delegate, &pending_path, true).await?;
+    auth.token(&[FULL_SCOPE]).await?;
+
+    tokio::fs::rename(&pending_path, TOKEN_PATH).await?;
+    tracing::info!("YouTube device authorization has finished");
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct StoredToken {
+    pub scopes: Vec<String>,
+    pub token: TokenInfo,
+}
+
+/// Tokens currently persisted in `token.json`.
+pub async fn stored_tokens() -> Result<Vec<StoredToken>, Error> {
+    let contents = match tokio::fs::read(TOKEN_PATH).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(serde_json::from_slice(&contents)?)
+}
+
+/// Whether any stored token grants the full youtube scope.
+pub fn has_full_scope(tokens: &[StoredToken]) -> bool {
+    tokens.iter().any(|stored| stored.scopes.iter().any(|scope| scope == FULL_SCOPE.as_ref()))
+}
+
+/// Only an installed-flow token limited to youtube.upload keeps uploading with
+/// that scope; asking it for more would wait for a browser consent forever.
+async fn upload_scope() -> Result<Scope, Error> {
+    let tokens = stored_tokens().await?;
+    if use_device_flow() || tokens.is_empty() || has_full_scope(&tokens) {
+        Ok(FULL_SCOPE)
+    } else {
+        Ok(Scope::Upload)
+    }
+}
+
+/// Editing fails right away instead of starting a consent the bot can't finish.
+async fn edit_scope() -> Result<Scope, Error> {
+    if !use_device_flow() && !has_full_scope(&stored_tokens().await?) {
+        return Err("The stored YouTube token only allows uploads. Authorize YouTube once more to edit videos and playlists".into());
+    }
+    Ok(FULL_SCOPE)
+}
+
+const BASE_TAGS: [&str; 5] = ["osu", "switzerland", "osc", "osu!swisscommunity", "osuswiss"];
+
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter)]
+pub enum Privacy {
+    #[name = "Public"]
+    Public,
+    #[name = "Unlisted"]
+    Unlisted,
+    #[name = "Private"]
+    Private,
+}
+
+impl Privacy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Public => "public",
+            Self::Unlisted => "unlisted",
+            Self::Private => "private",
+        }
+    }
+
+    pub fn parse(privacy: &str) -> Option<Self> {
+        match privacy {
+            "public" => Some(Self::Public),
+            "unlisted" => Some(Self::Unlisted),
+            "private" => Some(Self::Private),
+            _ => None,
+        }
+    }
+}
+
+/// Per-upload visibility settings. Anything not given on the command falls back
+/// to `OSC_BOT_YOUTUBE_PRIVACY` / `OSC_BOT_YOUTUBE_PLAYLISTS`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublishOptions {
+    pub privacy: Privacy,
+    /// Unix timestamp at which YouTube makes the video public.
+    pub publish_at: Option<i64>,
+    pub playlists: Vec<String>,
+}
 
-    let hub = YouTube::new(client, auth);
+impl Default for PublishOptions {
+    fn default() -> Self {
+        Self {
+            privacy: *YOUTUBE_PRIVACY,
+            publish_at: None,
+            playlists: YOUTUBE_PLAYLISTS.clone(),
+        }
+    }
+}
+
+/// Split a comma separated list of playlist ids.
+pub fn parse_playlists(playlists: &str) -> Vec<String> {
+    playlists.split(',').map(str::trim).filter(|id| !id.is_empty()).map(String::from).collect()
+}
+
+/// Parse a schedule time given as RFC 3339 (`2026-10-20T18:00:00+02:00`) or as
+/// `YYYY-MM-DD HH:MM` in UTC.
+pub fn parse_publish_at(publish_at: &str) -> Result<i64, Error> {
+    let publish_at = publish_at.trim();
+    if let Ok(date) = time::OffsetDateTime::parse(publish_at, &time::format_description::well_known::Rfc3339) {
+        return Ok(date.unix_timestamp());
+    }
+    let fmt = time::format_description::parse_borrowed::<2>("[year]-[month]-[day] [hour]:[minute]")?;
+    let date = time::PrimitiveDateTime::parse(publish_at, &fmt)
+        .map_err(|_| format!("Could not read `{}` as a date, use `YYYY-MM-DD HH:MM` (UTC)", publish_at))?;
+    Ok(date.assume_utc().unix_timestamp())
+}
+
+// thank god for chatGPT
+pub async fn upload(
+    video_path: &String,
+    title: String,
+    description: String,
+    tags: Vec<String>,
+    thumbnail: Vec<u8>,
+    options: &PublishOptions,
+) -> Result<String, Error> {
+    tracing::info!("Uploading to youtube...");
+
+    let hub = hub().await?;
+    let scope = upload_scope().await?;
 
     let mut video = Video::default();
 
-    let mut snippet = VideoSnippet::default();
-    snippet.title = Some(title);
-    snippet.description = Some(description);
-    snippet.tags = Some(vec!["osu".into(), "switzerland".into(), "osc".into(), "osu!swisscommunity".into(), "osuswiss".into(), ]);
-    snippet.category_id = Some("20".into());
+    let snippet = VideoSnippet {
+        title: Some(title),
+        description: Some(description),
+        tags: Some(BASE_TAGS.iter().map(|tag| tag.to_string()).chain(tags).collect()),
+        category_id: Some("20".into()),
+        ..Default::default()
+    };
 
     let mut status = VideoStatus::default();
-    status.privacy_status = Some("unlisted".to_string()); // "public" | "unlisted" | "private"
+    // YouTube only schedules private videos; they flip to public at publishAt.
+    let privacy = if options.publish_at.is_some() { Privacy::Private } else { options.privacy };
+    status.privacy_status = Some(privacy.as_str().to_string());
+    status.publish_at = match options.publish_at {
+        Some(timestamp) => Some(chrono::DateTime::from_timestamp(timestamp, 0).ok_or("Publish time is out of range")?),
+        None => None,
+    };
     status.self_declared_made_for_kids = Some(false);
     video.snippet = Some(snippet);
     video.status = Some(status);
@@ -89,8 +306,7 @@ pub async fn upload(video_path: &String, title: String, description: String, thu
     let (_resp, uploaded_video) = hub
         .videos()
         .insert(video)
-        // minimal scope for uploads:
-        .add_scope(youtube::api::Scope::Upload)
+        .add_scope(scope)
         // ensure these parts appear in the response:
         .add_part("snippet")
         .add_part("status")
@@ -100,23 +316,157 @@ pub async fn upload(video_path: &String, title: String, description: String, thu
     tracing::info!(video_id = uploaded_video.id, "Video has been uploaded");
     let video_id = uploaded_video.id.unwrap();
 
+    // The video is up at this point, so a playlist that refuses it only costs
+    // the playlist entry, not the upload.
+    for playlist_id in &options.playlists {
+        if let Err(e) = add_to_playlist(&hub, &video_id, playlist_id).await {
+            tracing::error!(playlist = playlist_id, error = %e, "Could not add video to playlist");
+        }
+    }
+
     if thumbnail.is_empty() {
         tracing::info!("No thumbnail bytes provided; skipping thumbnail set");
         return Ok(video_id);
     }
 
-    let thumb_mime = "image/png".parse().unwrap(); // or "image/jpeg"
+    set_thumbnail_with(&hub, &video_id, thumbnail, "image/png", scope).await?;
+
+    Ok(video_id)
+}
+
+pub async fn set_thumbnail(video_id: &str, thumbnail: Vec<u8>, mime: &str) -> Result<(), Error> {
+    let hub = hub().await?;
+    set_thumbnail_with(&hub, video_id, thumbnail, mime, upload_scope().await?).await
+}
+
+async fn set_thumbnail_with(hub: &YouTube<Connector>, video_id: &str, thumbnail: Vec<u8>, mime: &str, scope: Scope) -> Result<(), Error> {
+    let thumb_mime = mime.parse()?;
 
     tracing::info!("Setting thumbnail...");
     // set thumbnail
     hub
         .thumbnails()
-        .set(&video_id)
-        .add_scope(youtube::api::Scope::Upload)
+        .set(video_id)
+        .add_scope(scope)
         .upload(Cursor::new(thumbnail), thumb_mime)
         .await?;
 
     tracing::info!("Thumbnail has been set!");
+    Ok(())
+}
 
-    Ok(video_id)
-}
\ No newline at end of file
+/// Accept a bare video id or any of the usual YouTube / YouTube Studio links.
+pub fn parse_video_id(input: &str) -> String {
+    let input = input.trim();
+    let Ok(url) = url::Url::parse(input) else {
+        return input.to_string();
+    };
+    if let Some((_, id)) = url.query_pairs().find(|(key, _)| key == "v") {
+        return id.into_owned();
+    }
+    let segments: Vec<&str> = url.path_segments().map(|segments| segments.filter(|s| !s.is_empty()).collect()).unwrap_or_default();
+    match segments.as_slice() {
+        // studio.youtube.com/video/<id>/edit, youtube.com/shorts/<id>
+        ["video" | "shorts" | "embed", id, ..] => id.to_string(),
+        // youtu.be/<id>
+        [id] => id.to_string(),
+        _ => input.to_string(),
+    }
+}
+
+async fn fetch_video(hub: &YouTube<Connector>, video_id: &str, scope: Scope) -> Result<Video, Error> {
+    let (_resp, list) = hub
+        .videos()
+        .list(&vec!["snippet".into(), "status".into()])
+        .add_id(video_id)
+        .add_scope(scope)
+        .doit()
+        .await?;
+    Ok(list.items.and_then(|items| items.into_iter().next()).ok_or("Video does not exist on YouTube anymore")?)
+}
+
+/// Change title, description and/or privacy of a video. Whatever is `None`
+/// keeps its current value; videos.update replaces whole parts, so the rest of
+/// the snippet and status is read first and sent back unchanged.
+pub async fn update_video(video_id: &str, title: Option<String>, description: Option<String>, privacy: Option<Privacy>) -> Result<(), Error> {
+    let scope = edit_scope().await?;
+    let hub = hub().await?;
+    let current = fetch_video(&hub, video_id, scope).await?;
+    let current_snippet = current.snippet.unwrap_or_default();
+
+    let snippet = VideoSnippet {
+        title: title.or(current_snippet.title),
+        description: description.or(current_snippet.description),
+        tags: current_snippet.tags,
+        category_id: current_snippet.category_id,
+        default_language: current_snippet.default_language,
+        default_audio_language: current_snippet.default_audio_language,
+        ..Default::default()
+    };
+    let mut status = current.status.unwrap_or_default();
+    if let Some(privacy) = privacy {
+        status.privacy_status = Some(privacy.as_str().to_string());
+        // A schedule only works on private videos, and picking a privacy by
+        // hand replaces it.
+        status.publish_at = None;
+    }
+
+    let video = Video {
+        id: Some(video_id.to_string()),
+        snippet: Some(snippet),
+        status: Some(status),
+        ..Default::default()
+    };
+    hub
+        .videos()
+        .update(video)
+        .add_scope(scope)
+        .doit()
+        .await?;
+    tracing::info!(video_id = video_id, "Video has been updated");
+    Ok(())
+}
+
+async fn add_to_playlist(hub: &YouTube<Connector>, video_id: &str, playlist_id: &str) -> Result<(), Error> {
+    let scope = edit_scope().await?;
+    let item = PlaylistItem {
+        snippet: Some(PlaylistItemSnippet {
+            playlist_id: Some(playlist_id.to_string()),
+            resource_id: Some(ResourceId {
+                kind: Some("youtube#video".to_string()),
+                video_id: Some(video_id.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    hub
+        .playlist_items()
+        .insert(item)
+        .add_scope(scope)
+        .doit()
+        .await?;
+    tracing::info!(video_id = video_id, playlist = playlist_id, "Video has been added to playlist");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn video_ids_are_read_from_links() {
+        assert_eq!(parse_video_id("dQw4w9WgXcQ"), "dQw4w9WgXcQ");
+        assert_eq!(parse_video_id("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42"), "dQw4w9WgXcQ");
+        assert_eq!(parse_video_id("https://youtu.be/dQw4w9WgXcQ"), "dQw4w9WgXcQ");
+        assert_eq!(parse_video_id("https://studio.youtube.com/video/dQw4w9WgXcQ/edit"), "dQw4w9WgXcQ");
+    }
+
+    #[test]
+    fn score_references_are_left_alone() {
+        assert_eq!(parse_video_id("4567890123"), "4567890123");
+        assert_eq!(parse_video_id("d41d8cd98f00b204e9800998ecf8427e"), "d41d8cd98f00b204e9800998ecf8427e");
+    }
+}
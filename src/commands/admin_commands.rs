@@ -1,6 +1,6 @@
 use poise::{CreateReply, serenity_prelude::{self as serenity, CreateEmbed, Mentionable}};
 
-use crate::{Context, Error, db::{self, entities::user}, discord_helper::{MessageState, user_has_replay_role}, embeds::single_text_response, osu};
+use crate::{Context, Error, apis::youtube as youtube_api, defaults::{RANK_ROLES, YOUTUBE_ADMIN}, events::background_tasks::{rank_roles, refresh_feed}, db::{self, entities::{suggestion_limit, user}}, discord_helper::{MessageState, user_has_replay_role}, embeds::{self, single_text_response}};
 
 use sea_orm::{ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
 
@@ -12,8 +12,16 @@ async fn has_replay_role(ctx: Context<'_>) -> Result<bool, Error> {
     Ok(true)
 }
 
+/// The token decides which channel uploads go to, so only `OSC_BOT_YOUTUBE_ADMIN` may replace it.
+async fn is_youtube_admin(ctx: Context<'_>) -> Result<bool, Error> {
+    if *YOUTUBE_ADMIN != Some(ctx.author().id) {
+        single_text_response(&ctx, "Only the YouTube admin (OSC_BOT_YOUTUBE_ADMIN) can authorize YouTube", MessageState::INFO, true).await;
+        return Ok(false);
+    }
+    Ok(true)
+}
 
-#[poise::command(slash_command, rename = "admin", subcommands("blacklist"), check="has_replay_role")]
+#[poise::command(slash_command, rename = "admin", subcommands("blacklist", "youtube", "feed", "limits", "rank_roles_preview"), check="has_replay_role")]
 pub async fn bundle(_ctx: Context<'_>, _arg: String) -> Result<(), Error> { Ok(()) }
 
 #[poise::command(slash_command, subcommands("add", "remove", "list"))]
@@ -26,27 +34,7 @@ pub async fn add(
 ) -> Result<(), Error> {
     let user_id: i64 = user.user.id.into();
 
-    let mut db_user: user::ActiveModel = match db::get_user_by_discord_id(user_id).await? {
-        Some(user) => {
-            user.into()
-        },
-        None => {
-            let player = match osu::get_osu_instance().user(user.display_name()).await {
-                Ok(player) => player.user_id,
-                _ => {
-                    single_text_response(&ctx, &format!("User {} has the wrong username. please inform a moderator!", user.mention().to_string()), MessageState::SUCCESS, false).await;
-                    0
-                }
-            };
-            
-            user::ActiveModel {
-                discord_id: Set(user_id),
-                osu_id: Set(player as i64),
-                ..Default::default()
-            }.insert(&db::get_db()).await?.into()
-        }
-    };
-
+    let mut db_user: user::ActiveModel = db::get_user_by_discord_id_or_create(user_id).await?.into();
     db_user.is_blacklisted = Set(true);
     db_user.update(&db::get_db()).await?;
 
@@ -60,26 +48,7 @@ pub async fn remove(
     user: serenity::Member
 ) -> Result<(), Error> {
     let user_id: i64 = user.user.id.into();
-    let mut db_user: user::ActiveModel = match db::get_user_by_discord_id(user_id).await? {
-        Some(user) => {
-            user.into()
-        },
-        None => {
-            let player = match osu::get_osu_instance().user(user.display_name()).await {
-                Ok(player) => player.user_id,
-                _ => {
-                    single_text_response(&ctx, &format!("User {} has the wrong username. please inform a moderator!", user.mention().to_string()), MessageState::SUCCESS, false).await;
-                    0
-                }
-            };
-            
-            user::ActiveModel {
-                discord_id: Set(user_id),
-                osu_id: Set(player as i64),
-                ..Default::default()
-            }.insert(&db::get_db()).await?.into()
-        }
-    };
+    let mut db_user: user::ActiveModel = db::get_user_by_discord_id_or_create(user_id).await?.into();
 
     db_user.is_blacklisted = Set(false);
     db_user.update(&db::get_db()).await?;
@@ -104,4 +73,135 @@ pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
     let blacklist_embed = CreateEmbed::default().title("Blacklist").description(blacklist_content);
     ctx.send(CreateReply::default().embed(blacklist_embed)).await?;
     Ok(())
-}
\ No newline at end of file
+}
+
+#[poise::command(slash_command, subcommands("authorize", "status"))]
+pub async fn youtube(_ctx: Context<'_>, _arg: String) -> Result<(), Error> { Ok(()) }
+
+/// Connect the YouTube channel without a browser on the server
+#[poise::command(slash_command, check = "is_youtube_admin")]
+pub async fn authorize(ctx: Context<'_>) -> Result<(), Error> {
+    let http = ctx.serenity_context().http.clone();
+    let user = YOUTUBE_ADMIN.ok_or("OSC_BOT_YOUTUBE_ADMIN is not set")?;
+
+    tokio::spawn(async move {
+        let result = youtube_api::authorize_with_device_flow(http.clone(), user).await;
+        let embed = match result {
+            Ok(()) => embeds::single_text_response_embed("YouTube has been authorized. New tokens are in use from the next upload on.", MessageState::SUCCESS),
+            Err(e) => {
+                tracing::error!(error = %e, "YouTube device authorization failed");
+                embeds::single_text_response_embed(&format!("YouTube authorization failed: {}", e), MessageState::ERROR)
+            }
+        };
+        user.direct_message(&http, serenity::CreateMessage::default().embed(embed)).await.ok();
+    });
+
+    single_text_response(&ctx, "Check your DMs for the YouTube authorization code.", MessageState::INFO, true).await;
+    Ok(())
+}
+
+/// Show the stored YouTube token
+#[poise::command(slash_command)]
+pub async fn status(ctx: Context<'_>) -> Result<(), Error> {
+    let tokens = youtube_api::stored_tokens().await?;
+    ctx.send(CreateReply::default().embed(embeds::youtube_status_embed(&tokens)).ephemeral(true)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, subcommands("feed_add", "feed_remove", "feed_list"))]
+pub async fn feed(_ctx: Context<'_>, _arg: String) -> Result<(), Error> { Ok(()) }
+
+/// Announce new videos of a YouTube channel in a Discord channel
+#[poise::command(slash_command, rename = "add")]
+pub async fn feed_add(
+    ctx: Context<'_>,
+    #[description = "YouTube channel id (UC...)"] youtube_channel: String,
+    #[description = "channel the videos are announced in"] channel: serenity::GuildChannel,
+    #[description = "announcement text, {title} and {link} are filled in"] template: Option<String>,
+    #[description = "role pinged with every announcement"] role: Option<serenity::Role>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let youtube_channel = youtube_channel.trim().to_string();
+    if let Err(e) = refresh_feed::fetch_feed(&youtube_channel).await {
+        single_text_response(&ctx, &format!("Could not read the feed of ``{}``: {}", youtube_channel, e), MessageState::WARN, true).await;
+        return Ok(());
+    }
+    let exists = db::feed_subscriptions().await?.iter()
+        .any(|subscription| subscription.youtube_channel_id == youtube_channel && subscription.discord_channel_id == channel.id.get() as i64);
+    if exists {
+        single_text_response(&ctx, &format!("{} already announces ``{}``", channel.mention(), youtube_channel), MessageState::INFO, true).await;
+        return Ok(());
+    }
+
+    let subscription = db::add_feed_subscription(youtube_channel, channel.id.get() as i64, template, role.map(|role| role.id.get() as i64)).await?;
+    single_text_response(&ctx, &format!("Feed #{} added. Videos uploaded from now on are announced in {}", subscription.id, channel.mention()), MessageState::SUCCESS, true).await;
+    Ok(())
+}
+
+/// Stop announcing a feed
+#[poise::command(slash_command, rename = "remove")]
+pub async fn feed_remove(
+    ctx: Context<'_>,
+    #[description = "feed number from /admin feed list"] id: i64,
+) -> Result<(), Error> {
+    if db::remove_feed_subscription(id).await? {
+        single_text_response(&ctx, &format!("Feed #{} has been removed", id), MessageState::SUCCESS, true).await;
+    } else {
+        single_text_response(&ctx, &format!("There is no feed #{}", id), MessageState::WARN, true).await;
+    }
+    Ok(())
+}
+
+/// List the announced feeds
+#[poise::command(slash_command, rename = "list")]
+pub async fn feed_list(ctx: Context<'_>) -> Result<(), Error> {
+    let subscriptions = db::feed_subscriptions().await?;
+    ctx.send(CreateReply::default().embed(embeds::feed_subscriptions_embed(&subscriptions)).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Show or change the suggestion limits. 0 turns a limit off
+#[poise::command(slash_command)]
+pub async fn limits(
+    ctx: Context<'_>,
+    #[description = "pending suggestions per user"] max_pending: Option<u32>,
+    #[description = "minutes between two suggestions of a user"] cooldown_minutes: Option<u32>,
+    #[description = "suggestions per user within 24 hours"] daily_cap: Option<u32>,
+) -> Result<(), Error> {
+    let current = db::get_suggestion_limits().await?;
+    let limit = |value: Option<u32>, current: Option<i64>, scale: i64| match value {
+        Some(0) => None,
+        Some(value) => Some(value as i64 * scale),
+        None => current,
+    };
+    let limits = suggestion_limit::Model {
+        max_pending: limit(max_pending, current.max_pending, 1),
+        cooldown_seconds: limit(cooldown_minutes, current.cooldown_seconds, 60),
+        daily_cap: limit(daily_cap, current.daily_cap, 1),
+        ..current
+    };
+    if limits != current {
+        db::set_suggestion_limits(limits.clone()).await?;
+    }
+
+    ctx.send(CreateReply::default().embed(embeds::suggestion_limits_embed(&limits)).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Show the rank role changes the sync would make. Set apply to make them now
+#[poise::command(slash_command, rename = "rank_roles")]
+pub async fn rank_roles_preview(
+    ctx: Context<'_>,
+    #[description = "add and remove the roles instead of only listing them"] apply: Option<bool>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    if RANK_ROLES.is_empty() {
+        single_text_response(&ctx, "No rank roles are configured", MessageState::INFO, true).await;
+        return Ok(());
+    }
+
+    let dry_run = !apply.unwrap_or(false);
+    let changes = rank_roles::sync_rank_roles(ctx.serenity_context(), dry_run).await?;
+    ctx.send(CreateReply::default().embed(embeds::rank_role_changes_embed(&changes, dry_run)).ephemeral(true)).await?;
+    Ok(())
+}
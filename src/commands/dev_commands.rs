@@ -1,14 +1,12 @@
-use std::fs::remove_file;
-
 use poise::serenity_prelude::{self as serenity, CreateActionRow, CreateAttachment};
-use crate::apis::{youtube};
+use crate::apis::youtube::{self, PublishOptions};
 use crate::embeds::single_text_response;
 use crate::{Context, Error, embeds};
 use crate::osu;
 use crate::discord_helper::{MessageState};
 use crate::generate::thumbnail;
 
-#[poise::command(slash_command, rename = "dev", subcommands("test_osu_client", "test_thumbnail", "regenerate_token", "test_upload"))]
+#[poise::command(slash_command, rename = "dev", subcommands("test_osu_client", "test_thumbnail", "test_upload"))]
 pub async fn bundle(_ctx: Context<'_>, _arg: String) -> Result<(), Error> { Ok(()) }
 
 #[poise::command(slash_command)]
@@ -43,16 +41,7 @@ pub async fn test_thumbnail(ctx: Context<'_>) -> Result<(), Error> {
 #[poise::command(slash_command)]
 pub async fn test_upload(ctx: Context<'_>) -> Result<(), Error> {
     ctx.defer_ephemeral().await?;
-    youtube::upload(&"videoForRegen/random.mp4".into(), "test".into(), "test".into(), vec![]).await?;
+    youtube::upload(&"videoForRegen/random.mp4".into(), "test".into(), "test".into(), vec![], vec![], &PublishOptions::default()).await?;
     single_text_response(&ctx, "video has been uploaded!", MessageState::SUCCESS, true).await;
     Ok(())
 }
-
-#[poise::command(slash_command)]
-pub async fn regenerate_token(ctx: Context<'_>) -> Result<(), Error> {
-    ctx.defer_ephemeral().await?;
-    remove_file("token.json").ok();
-    youtube::upload(&"videoForRegen/random.mp4".into(), "test".into(), "test".into(), vec![]).await?;
-    single_text_response(&ctx, "regenerated token!", MessageState::SUCCESS, true).await;
-    Ok(())
-}
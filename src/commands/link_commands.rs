@@ -0,0 +1,89 @@
+use poise::{CreateReply, serenity_prelude::{self as serenity, CreateActionRow, CreateButton, Mentionable}};
+
+use crate::{Context, Error, apis::osu_link, db, discord_helper::MessageState, embeds::{single_text_response, single_text_response_embed}, osu};
+
+/// Link your osu! account to your Discord account
+#[poise::command(slash_command)]
+pub async fn link(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    if !osu_link::is_enabled() {
+        single_text_response(&ctx, "Account linking isn't set up on this bot", MessageState::WARN, true).await;
+        return Ok(());
+    }
+
+    let mut text = "Authorize the bot on osu! to link your account. The button works for 10 minutes.".to_string();
+    if let Some(linked) = db::get_linked_user(ctx.author().id.into()).await? {
+        text = format!(
+            "{}\nYou are currently linked to **{}**; authorizing replaces that link.",
+            text,
+            linked.osu_username.unwrap_or_else(|| linked.osu_id.to_string())
+        );
+    }
+
+    let button = CreateButton::new_link(osu_link::authorize_url(ctx.author().id)?).label("Authorize on osu!");
+    ctx.send(
+        CreateReply::default()
+            .embed(single_text_response_embed(&text, MessageState::INFO))
+            .components(vec![CreateActionRow::Buttons(vec![button])])
+            .ephemeral(true),
+    ).await?;
+    Ok(())
+}
+
+/// Remove the link between your Discord and osu! account
+#[poise::command(slash_command)]
+pub async fn unlink(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    if db::unlink_user(ctx.author().id.into()).await? {
+        single_text_response(&ctx, "Your osu! account has been unlinked", MessageState::SUCCESS, true).await;
+    } else {
+        single_text_response(&ctx, "You haven't linked an osu! account", MessageState::INFO, true).await;
+    }
+    Ok(())
+}
+
+/// Show which osu! account a member linked, or which member linked an osu! account
+#[poise::command(slash_command)]
+pub async fn whois(
+    ctx: Context<'_>,
+    #[description = "leave both empty to show yourself"] member: Option<serenity::Member>,
+    #[description = "osu! username"] player: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    if let Some(player) = player {
+        let osu_user = match osu::get_osu_instance().user(&player).await {
+            Ok(osu_user) => osu_user,
+            Err(_) => {
+                single_text_response(&ctx, &format!("Could not find player ``{}``", player), MessageState::WARN, false).await;
+                return Ok(());
+            }
+        };
+        let text = match db::get_linked_user_by_osu_id(osu_user.user_id as i64).await? {
+            Some(linked) => format!(
+                "[{}](https://osu.ppy.sh/users/{}) is <@{}>",
+                osu_user.username, osu_user.user_id, linked.discord_id
+            ),
+            None => format!("Nobody linked [{}](https://osu.ppy.sh/users/{})", osu_user.username, osu_user.user_id),
+        };
+        single_text_response(&ctx, &text, MessageState::INFO, false).await;
+        return Ok(());
+    }
+
+    let user = match &member {
+        Some(member) => member.user.clone(),
+        None => ctx.author().clone(),
+    };
+    let text = match db::get_linked_user(user.id.into()).await? {
+        Some(linked) => format!(
+            "{} is [{}](https://osu.ppy.sh/users/{}), linked <t:{}:R>",
+            user.mention(),
+            linked.osu_username.unwrap_or_else(|| linked.osu_id.to_string()),
+            linked.osu_id,
+            linked.linked_at.unwrap_or_default()
+        ),
+        None => format!("{} hasn't linked an osu! account", user.mention()),
+    };
+    single_text_response(&ctx, &text, MessageState::INFO, false).await;
+    Ok(())
+}
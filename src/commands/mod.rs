@@ -2,9 +2,10 @@ use crate::{Data, Error};
 
 mod dev_commands;
 mod replay_commands;
-mod suggest_commands;
+pub mod suggest_commands;
 mod skin_commands;
 mod admin_commands;
+mod link_commands;
 
 pub fn slash_commands_bundle() -> Vec<poise::Command<Data, Error>> {
 
@@ -13,6 +14,9 @@ pub fn slash_commands_bundle() -> Vec<poise::Command<Data, Error>> {
             suggest_commands::bundle(),
             skin_commands::bundle(),
             admin_commands::bundle(),
+            link_commands::link(),
+            link_commands::unlink(),
+            link_commands::whois(),
     ];
 
     if cfg!(debug_assertions) {
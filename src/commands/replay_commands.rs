@@ -1,15 +1,14 @@
 use poise::{CreateReply, serenity_prelude as serenity};
 use rosu_v2::prelude as rosu;
 use rosu_v2::prelude::BeatmapExtended;
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
-use crate::db::entities::user;
 use crate::discord_helper::{ContextForFunctions, MessageState};
 use crate::embeds::{single_text_response, single_text_response_embed};
-use crate::osu::formatter::convert_osu_db_to_mod_array;
 use crate::{Context, Error, db, embeds};
 
 use crate::osu;
-use crate::generate::{danser, thumbnail, upload, youtube_text};
+use crate::generate::{danser, render_queue, thumbnail, youtube_text};
+use crate::generate::uploader::UploadDestination;
+use crate::apis::youtube::{self, Privacy, PublishOptions};
 use crate::discord_helper::user_has_replay_role;
 
 async fn has_replay_role(ctx: Context<'_>) -> Result<bool, Error> {
@@ -20,12 +19,15 @@ async fn has_replay_role(ctx: Context<'_>) -> Result<bool, Error> {
     Ok(true)
 }
 
-#[poise::command(slash_command, rename = "replay", subcommands("generate"), check = "has_replay_role")]
+#[poise::command(slash_command, rename = "replay", subcommands("generate", "video"), check = "has_replay_role")]
 pub async fn bundle(_ctx: Context<'_>, _arg: String) -> Result<(), Error> { Ok(()) }
 
 #[poise::command(slash_command, subcommands("thumbnail", "title_and_description", "render_and_upload"), check = "has_replay_role")]
 pub async fn generate(_ctx: Context<'_>, _arg: String) -> Result<(), Error> { Ok(()) }
 
+#[poise::command(slash_command, subcommands("edit"), check = "has_replay_role")]
+pub async fn video(_ctx: Context<'_>, _arg: String) -> Result<(), Error> { Ok(()) }
+
 /// Either select score id or score file
 #[poise::command(slash_command)]
 pub async fn thumbnail(
@@ -97,8 +99,8 @@ pub async fn title_and_description(
         };
         let map = osu::get_osu_instance().beatmap().map_id(score.map_id).await.expect("Beatmap exists");
         let title = youtube_text::generate_title_with_score(&score, &map).await;
-        let pp = osu::pp_calculator::calculate_score_by_score(&score).await.ok().map(|r| r.pp);
-        let description = youtube_text::generate_description(score.user_id, score.map_id, Some(&score), None, pp, None);
+        let rating = osu::pp_calculator::calculate_score_by_score(&score).await.ok();
+        let description = youtube_text::generate_description(score.user_id, score.map_id, Some(&score), None, rating.as_ref(), None);
         ctx.say(format!("```{}``````{}```", title, description)).await?;
     }
     else if scorefile.is_some() {
@@ -121,8 +123,8 @@ pub async fn title_and_description(
             },
         };
         let title = youtube_text::generate_title_with_replay(&replay, &map).await;
-        let pp = osu::pp_calculator::calculate_score_by_replay(&replay, &map).await.ok().map(|r| r.pp);
-        let description = youtube_text::generate_description(user.user_id, map.map_id, None, Some(timestamp), pp, None);
+        let rating = osu::pp_calculator::calculate_score_by_replay(&replay, &map).await.ok();
+        let description = youtube_text::generate_description(user.user_id, map.map_id, None, Some(timestamp), rating.as_ref(), None);
 
         ctx.say(format!("```{}``````{}```", title, description)).await?;
     }
@@ -142,7 +144,29 @@ pub async fn render_and_upload (
     #[description = "subtitle inside the thumbnail"] subtitle: Option<String>,
     #[description = "identifier for skin (searches by player)"] identifier: Option<String>,
     #[description = "skip beatmap download"] skip_beatmap_download: Option<bool>,
+    #[description = "where the video goes (defaults to YouTube)"] destination: Option<UploadDestination>,
+    #[description = "YouTube visibility (defaults to the configured privacy)"] privacy: Option<Privacy>,
+    #[description = "schedule the video as YYYY-MM-DD HH:MM (UTC)"] publish_at: Option<String>,
+    #[description = "comma separated YouTube playlist ids (defaults to the configured playlists)"] playlists: Option<String>,
 ) -> Result<(), Error> {
+    let destination = destination.unwrap_or(UploadDestination::Youtube);
+    let defaults = PublishOptions::default();
+    let publish_at = match publish_at.as_deref().map(youtube::parse_publish_at).transpose() {
+        Ok(publish_at) => publish_at,
+        Err(e) => {
+            single_text_response(&ctx, &e.to_string(), MessageState::WARN, true).await;
+            return Ok(());
+        }
+    };
+    if publish_at.is_some_and(|publish_at| publish_at <= time::OffsetDateTime::now_utc().unix_timestamp()) {
+        single_text_response(&ctx, "The scheduled time has to be in the future", MessageState::WARN, true).await;
+        return Ok(());
+    }
+    let publish = PublishOptions {
+        privacy: privacy.unwrap_or(defaults.privacy),
+        publish_at,
+        playlists: playlists.as_deref().map(youtube::parse_playlists).unwrap_or(defaults.playlists),
+    };
     ctx.defer().await?;
     let reply = ctx.send(CreateReply::default().embed(embeds::render_and_upload_embed(&"...".into(), false, None, false)?)).await?;
 
@@ -150,7 +174,8 @@ pub async fn render_and_upload (
         command_context: Some(ctx),
         reply: Some(reply),
         event_context: None,
-        component: None
+        component: None,
+        message: None,
     };
 
     if scoreid.is_some() {
@@ -170,16 +195,22 @@ pub async fn render_and_upload (
             cff.edit(single_text_response_embed("Rendering a gamemode other than standard is currently not possible.", MessageState::WARN), vec![]).await?;
             return Ok(());
         }
-        let replay = osu::get_osu_instance().replay_raw(score.id).await.unwrap();
         let map = osu::get_osu_instance().beatmap().map_id(score.map_id).await.expect("Beatmap exists");
-        let beatmap_hash = map.checksum.as_ref().unwrap().clone();
-        let replay_reference = score.id.to_string();
-        danser::attach_replay(&beatmap_hash, &replay_reference, &replay).await.unwrap();
-        let mods: Vec<rosu::GameMod> = score.mods.clone().into_iter().collect();
-        let user = user::Entity::find().filter(user::Column::OsuId.eq(score.user_id)).one(&db::get_db()).await?;
-        let acronym_mods: Vec<String> = mods.iter().map(|game_mod| game_mod.acronym().to_string()).collect();
-        let skin = danser::resolve_correct_skin(user, identifier, acronym_mods).await?;
-        upload::render_and_upload_by_score(&cff, score, map, subtitle, skin, skip_beatmap_download.unwrap_or(false)).await?;
+        let title = youtube_text::generate_title_with_score(&score, &map).await;
+        render_queue::enqueue(&cff, render_queue::RenderRequest {
+            kind: render_queue::JobKind::ScoreId,
+            reference: score.id.to_string(),
+            map_id: map.map_id,
+            title,
+            subtitle,
+            skin_identifier: identifier,
+            skip_beatmap_download: skip_beatmap_download.unwrap_or(false),
+            destination,
+            publish,
+            requested_by: ctx.author().id,
+            approved_by: ctx.author().id,
+            suggestion_id: None,
+        }).await?;
     }
     else if scorefile.is_some() {
         let bytes = scorefile.unwrap().download().await?;
@@ -194,7 +225,6 @@ pub async fn render_and_upload (
             cff.edit(single_text_response_embed("Rendering a gamemode other than standard is currently not possible.", MessageState::WARN), vec![]).await?;
             return Ok(());
         }
-        let player = osu::get_osu_instance().user(replay.player_name.as_ref().expect("Expect a username")).await.expect("Player to exist");
 
         let map: BeatmapExtended = match osu::get_beatmap_from_checksum(&replay.beatmap_hash).await {
             Some(map) => map,
@@ -206,10 +236,21 @@ pub async fn render_and_upload (
         let beatmap_hash = map.checksum.as_ref().unwrap().clone();
         let replay_reference = replay.replay_hash.as_ref().unwrap().clone();
         danser::attach_replay(&beatmap_hash, &replay_reference, &bytes).await?;
-        let db_user = user::Entity::find().filter(user::Column::OsuId.eq(player.user_id)).one(&db::get_db()).await?;
-        let mods = convert_osu_db_to_mod_array(replay.mods);
-        let skin = danser::resolve_correct_skin(db_user, identifier, mods).await?;
-        upload::render_and_upload_by_replay(&cff, replay, map, player, subtitle, skin, skip_beatmap_download.unwrap_or(false)).await?;
+        let title = youtube_text::generate_title_with_replay(&replay, &map).await;
+        render_queue::enqueue(&cff, render_queue::RenderRequest {
+            kind: render_queue::JobKind::ReplayFile,
+            reference: replay_reference,
+            map_id: map.map_id,
+            title,
+            subtitle,
+            skin_identifier: identifier,
+            skip_beatmap_download: skip_beatmap_download.unwrap_or(false),
+            destination,
+            publish,
+            requested_by: ctx.author().id,
+            approved_by: ctx.author().id,
+            suggestion_id: None,
+        }).await?;
     }
     else {
         embeds::single_text_response(&ctx, "Please define scoreid or scorefile", MessageState::WARN, false).await;
@@ -217,4 +258,52 @@ pub async fn render_and_upload (
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Change a video the bot uploaded. Options left empty keep their current value
+#[poise::command(slash_command)]
+pub async fn edit(
+    ctx: Context<'_>,
+    #[description = "video id, YouTube link, score id or replay hash"] video: String,
+    #[description = "new title"] #[max_length = 100] title: Option<String>,
+    #[description = "new description (the edit button on the upload allows multiple lines)"] description: Option<String>,
+    #[description = "new thumbnail (png or jpeg, up to 2 MB)"] thumbnail: Option<serenity::Attachment>,
+    #[description = "new visibility"] privacy: Option<Privacy>,
+) -> Result<(), Error> {
+    if title.is_none() && description.is_none() && thumbnail.is_none() && privacy.is_none() {
+        single_text_response(&ctx, "Please define what to change", MessageState::WARN, true).await;
+        return Ok(());
+    }
+    if let Some(thumbnail) = &thumbnail
+        && !matches!(thumbnail.content_type.as_deref(), Some("image/png" | "image/jpeg")) {
+        single_text_response(&ctx, "The thumbnail has to be a png or jpeg image", MessageState::WARN, true).await;
+        return Ok(());
+    }
+    ctx.defer().await?;
+
+    let video = match db::find_youtube_video(&youtube::parse_video_id(&video)).await? {
+        Some(video) => video,
+        None => {
+            single_text_response(&ctx, "This video was not uploaded by the bot", MessageState::WARN, false).await;
+            return Ok(());
+        }
+    };
+
+    if title.is_some() || description.is_some() || privacy.is_some() {
+        if let Err(e) = youtube::update_video(&video.video_id, title.clone(), description.clone(), privacy).await {
+            single_text_response(&ctx, &format!("Could not update the video: {e}"), MessageState::ERROR, false).await;
+            return Ok(());
+        }
+        db::update_youtube_video(&video.video_id, title.clone(), description).await?;
+    }
+    if let Some(thumbnail) = thumbnail {
+        let mime = thumbnail.content_type.clone().unwrap_or_default();
+        if let Err(e) = youtube::set_thumbnail(&video.video_id, thumbnail.download().await?, &mime).await {
+            single_text_response(&ctx, &format!("Could not set the thumbnail: {e}"), MessageState::ERROR, false).await;
+            return Ok(());
+        }
+    }
+
+    ctx.send(CreateReply::default().embed(embeds::video_updated_embed(title.as_ref().unwrap_or(&video.title), &video.video_id))).await?;
+    Ok(())
+}
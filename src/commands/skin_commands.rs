@@ -1,7 +1,7 @@
-use poise::{CreateReply, serenity_prelude::{self as serenity, CreateEmbed, CreateEmbedAuthor}};
+use poise::{CreateReply, serenity_prelude::{self as serenity, CreateEmbed, CreateEmbedAuthor, Mentionable}};
 use url::Url;
 
-use crate::{Context, Error, apis::osc_web, db, discord_helper::MessageState, embeds::single_text_response, osu};
+use crate::{Context, Error, apis::osc_web, db, discord_helper::MessageState, embeds::single_text_response};
 
 const OSC_WEB_HOME: &str = "https://skins.sulej.net/community/osc";
 
@@ -60,35 +60,26 @@ pub async fn get(
 ) -> Result<(), Error> {
     ctx.defer().await?;
 
-    let username = match &member {
-        Some(m) => m.display_name().to_string(),
-        None => match ctx.author_member().await {
-            Some(m) => m.display_name().to_string(),
-            None => ctx.author().name.clone(),
-        },
-    };
     let user_id: i64 = match &member {
         Some(m) => m.user.id.into(),
         None => ctx.author().id.into(),
     };
 
-    let player = match osu::get_osu_instance().user(&username).await {
-        Ok(u) => u,
-        Err(_) => {
-            single_text_response(
-                &ctx,
-                "I couldn't match that Discord name to an osu! user. Make sure your Discord display name matches your osu! username.",
-                MessageState::WARN,
-                false,
-            )
-            .await;
+    let linked = match db::get_linked_user(user_id).await? {
+        Some(linked) => linked,
+        None => {
+            let message = match &member {
+                Some(m) => format!("{} hasn't linked an osu! account yet.", m.mention()),
+                None => "You haven't linked your osu! account yet. Use `/link` first.".to_string(),
+            };
+            single_text_response(&ctx, &message, MessageState::WARN, false).await;
             return Ok(());
         }
     };
+    let osu_id = linked.osu_id;
+    let username = linked.osu_username.unwrap_or_else(|| osu_id.to_string());
 
-    db::get_user_by_discord_id_or_create(user_id, player.user_id as i32).await?;
-
-    let picks = match osc_web::get_user_picks(player.user_id as i64).await {
+    let picks = match osc_web::get_user_picks(osu_id).await {
         Ok(p) => p,
         Err(e) => {
             single_text_response(
@@ -107,7 +98,7 @@ pub async fn get(
         match picks.get(*slot).and_then(|v| v.as_ref()) {
             // Own picks and the community skin render plain (name + link); only
             // cross-user picks get a "from osu! id …" attribution.
-            Some(p) if p.is_community() || p.owner_osu_id == Some(player.user_id as i64) => {
+            Some(p) if p.is_community() || p.owner_osu_id == Some(osu_id) => {
                 let url = skin_doc_url(p);
                 lines.push(format!("**{slot}** — [{}]({})", p.dir_name, url));
             }
@@ -130,7 +121,7 @@ pub async fn get(
             username
         )))
         .description(lines.join("\n"))
-        .url(format!("https://skins.sulej.net/users/{}/picks", player.user_id));
+        .url(format!("https://skins.sulej.net/users/{}/picks", osu_id));
     ctx.send(CreateReply::default().embed(embed)).await.unwrap();
     Ok(())
 }
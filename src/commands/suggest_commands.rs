@@ -1,13 +1,185 @@
 use std::vec;
 
+use poise::CreateReply;
 use poise::serenity_prelude::{self as serenity, CreateButton, CreateEmbed, ReactionType};
 use rosu_v2::prelude as rosu;
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
-use crate::{Context, Error, db::{self, entities::user}, defaults::{self, EMPTY_VALUE}, discord_helper::MessageState, embeds, generate::danser, osu};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set};
+use crate::{Context, Error, db::{self, SuggestionFilter, entities::suggestion}, defaults, discord_helper::{MessageState, user_has_replay_role}, embeds::{self, single_text_response}, generate::{danser, render_queue::JobKind}, osu};
 
-#[poise::command(slash_command, rename = "suggest", subcommands("score"), required_permissions = "SEND_MESSAGES")]
+/// Suggestions listed per page of `/suggest queue`.
+const QUEUE_PAGE_SIZE: u64 = 10;
+
+async fn has_replay_role(ctx: Context<'_>) -> Result<bool, Error> {
+    if !user_has_replay_role(ctx, ctx.author()).await.unwrap() {
+        single_text_response(&ctx, "No permission L", MessageState::INFO, true).await;
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+#[poise::command(slash_command, rename = "suggest", subcommands("score", "queue"), required_permissions = "SEND_MESSAGES")]
 pub async fn bundle(_ctx: Context<'_>, _arg: String) -> Result<(), Error> { Ok(()) }
 
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter)]
+pub enum ModeFilter {
+    #[name = "osu!standard"]
+    Osu,
+    #[name = "osu!taiko"]
+    Taiko,
+    #[name = "osu!catch"]
+    Catch,
+    #[name = "osu!mania"]
+    Mania,
+}
+
+impl From<ModeFilter> for rosu::GameMode {
+    fn from(mode: ModeFilter) -> Self {
+        match mode {
+            ModeFilter::Osu => rosu::GameMode::Osu,
+            ModeFilter::Taiko => rosu::GameMode::Taiko,
+            ModeFilter::Catch => rosu::GameMode::Catch,
+            ModeFilter::Mania => rosu::GameMode::Mania,
+        }
+    }
+}
+
+/// List pending suggestions, oldest first
+#[poise::command(slash_command, check = "has_replay_role")]
+pub async fn queue(
+    ctx: Context<'_>,
+    #[description = "only suggestions of this osu! player"] player: Option<String>,
+    #[description = "only suggestions in this mode"] mode: Option<ModeFilter>,
+    #[description = "only suggestions older than this many days"] older_than_days: Option<u32>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let osu_user_id = match player {
+        Some(player) => match osu::get_osu_instance().user(&player).await {
+            Ok(user) => Some(user.user_id as i64),
+            Err(_) => {
+                single_text_response(&ctx, &format!("Could not find player ``{}``", player), MessageState::WARN, true).await;
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+    let filter = SuggestionFilter {
+        osu_user_id,
+        mode: mode.map(|mode| rosu::GameMode::from(mode) as i64),
+        older_than_days,
+    };
+
+    let (embed, components) = queue_page(&filter, 0).await?;
+    ctx.send(CreateReply::default().embed(embed).components(components).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Render one page of the queue together with its navigation buttons. The
+/// buttons carry page and filter (`suggestionQueue:page:player:mode:days:button`)
+/// so they keep working without any state in the bot.
+pub async fn queue_page(filter: &SuggestionFilter, page: u64) -> Result<(CreateEmbed, Vec<serenity::CreateActionRow>), Error> {
+    let (mut suggestions, pages) = db::pending_suggestions(filter, page, QUEUE_PAGE_SIZE).await?;
+    // Suggestions handled since the page was rendered can make it run past the end.
+    let page = page.min(pages.saturating_sub(1));
+    if suggestions.is_empty() && pages > 0 {
+        suggestions = db::pending_suggestions(filter, page, QUEUE_PAGE_SIZE).await?.0;
+    }
+
+    let encode = |value: Option<i64>| value.map(|value| value.to_string()).unwrap_or("-".to_string());
+    let custom_id = |target: u64, button: &str| format!(
+        "suggestionQueue:{}:{}:{}:{}:{}",
+        target, encode(filter.osu_user_id), encode(filter.mode), encode(filter.older_than_days.map(i64::from)), button,
+    );
+    let buttons = vec![
+        CreateButton::new(custom_id(page.saturating_sub(1), "previous"))
+            .emoji(ReactionType::Unicode("◀️".to_string()))
+            .style(serenity::ButtonStyle::Secondary)
+            .disabled(page == 0),
+        CreateButton::new(custom_id(page, "refresh"))
+            .emoji(ReactionType::Unicode("🔄".to_string()))
+            .style(serenity::ButtonStyle::Secondary),
+        CreateButton::new(custom_id(page + 1, "next"))
+            .emoji(ReactionType::Unicode("▶️".to_string()))
+            .style(serenity::ButtonStyle::Secondary)
+            .disabled(page + 1 >= pages),
+    ];
+
+    Ok((embeds::suggestion_queue_embed(&suggestions, page, pages), vec![serenity::CreateActionRow::Buttons(buttons)]))
+}
+
+/// Read back what `queue_page` put into a button.
+pub fn parse_queue_custom_id(data: &[&str]) -> Result<(SuggestionFilter, u64), Error> {
+    let [page, osu_user_id, mode, older_than_days, ..] = *data else {
+        return Err(format!("Unexpected suggestion queue data {:?}", data).into());
+    };
+    let decode = |value: &str| -> Result<Option<i64>, Error> {
+        Ok(if value == "-" { None } else { Some(value.parse()?) })
+    };
+    let filter = SuggestionFilter {
+        osu_user_id: decode(osu_user_id)?,
+        mode: decode(mode)?,
+        older_than_days: decode(older_than_days)?.map(|days| days as u32),
+    };
+    Ok((filter, page.parse()?))
+}
+
+/// Explain why `discord_id` may not suggest right now and when that changes,
+/// or `None` if the suggestion limits allow another one.
+async fn rate_limit_message(discord_id: i64) -> Result<Option<String>, Error> {
+    const DAY: i64 = 24 * 60 * 60;
+    let limits = db::get_suggestion_limits().await?;
+    let now = time::OffsetDateTime::now_utc().unix_timestamp();
+
+    if let Some(max_pending) = limits.max_pending {
+        let pending = db::count_pending_suggestions_by(discord_id).await?;
+        if pending as i64 >= max_pending {
+            return Ok(Some(format!(
+                "You already have {} pending suggestions. You can suggest again once one of them has been handled.", pending
+            )));
+        }
+    }
+
+    let window = limits.cooldown_seconds.unwrap_or(0).max(DAY);
+    let times = db::suggestion_times_by(discord_id, now - window).await?;
+
+    if let (Some(cooldown), Some(last)) = (limits.cooldown_seconds, times.last()) && last + cooldown > now {
+        return Ok(Some(format!("Please wait a bit between suggestions. You can suggest again <t:{}:R>.", last + cooldown)));
+    }
+
+    if let Some(daily_cap) = limits.daily_cap {
+        let today: Vec<i64> = times.into_iter().filter(|created_at| *created_at > now - DAY).collect();
+        if today.len() as i64 >= daily_cap {
+            // The oldest suggestion that still counts has to leave the 24 hour window.
+            let next = today[today.len() - daily_cap as usize] + DAY;
+            return Ok(Some(format!(
+                "You reached the limit of {} suggestions per day. You can suggest again <t:{}:R>.", daily_cap, next
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Why a suggestion was not posted, shown to the user who made it.
+pub struct Refusal(pub String, pub MessageState);
+
+impl Refusal {
+    fn warn(message: impl Into<String>) -> Self {
+        Refusal(message.into(), MessageState::WARN)
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Refusal(message.into(), MessageState::ERROR)
+    }
+}
+
+/// A suggestion that passed every check and only has to be posted.
+struct CheckedSuggestion {
+    embed: CreateEmbed,
+    mode: rosu::GameMode,
+    row: suggestion::ActiveModel,
+    identifier: Option<String>,
+}
+
 /// Either submit score id or score file
 #[poise::command(slash_command)]
 pub async fn score(
@@ -17,120 +189,192 @@ pub async fn score(
     #[description = "reason"] reason: Option<String>,
     #[description = "name of the skin. only accepts skins set by the player"] skin: Option<String>,
 ) -> Result<(), Error> {
-    let embed: CreateEmbed;
-    let mode: rosu::GameMode;
-    let parameters: String;
-    let requesting_user: u64 = ctx.author().id.into();
+    if let Some(message) = rate_limit_message(ctx.author().id.get() as i64).await? {
+        embeds::single_text_response(&ctx, &message, MessageState::WARN, true).await;
+        return Ok(());
+    }
     ctx.defer().await?;
-    
-    if scoreid.is_some() {
-        let unwrapped_score_id = scoreid.unwrap();
-        if db::has_score(unwrapped_score_id.to_string()).await? {
-            embeds::single_text_response(&ctx, &format!("Score {} has already been requested", unwrapped_score_id), MessageState::WARN, false).await;
-            return Ok(());
-        }
-        let score: rosu::Score = match osu::get_osu_instance().score(unwrapped_score_id).await {
-            Ok(score) => score,
-            Err(_) => {
-                embeds::single_text_response(&ctx, &format!("Score with id {} does not exist", unwrapped_score_id), MessageState::ERROR, false).await;
-                return Ok(());
-            }
-        };
-
-        if !score.has_replay {
-            embeds::single_text_response(&ctx, "Score has no replay to download. Please provide the replay file", MessageState::ERROR, false).await;
-            return Ok(());
-        }
 
-        let identifier = match skin {
-            Some(identifier) => {
-                match user::Entity::find().filter(user::Column::OsuId.eq(score.user_id as i64)).one(&db::get_db()).await? {
-                    Some(user) => {
-                        match db::get_skin_by_identifier(user, identifier.clone()).await? {
-                            Some(_) => identifier,
-                            None => {
-                                embeds::single_text_response(&ctx, &format!("Skin with that name {} does not exist", identifier).to_string(), MessageState::ERROR, false).await;
-                                return Ok(())
-                            }
-                        }
-                    },
-                    None => {
-                        embeds::single_text_response(&ctx, &format!("Skin with that name {} does not exist", identifier).to_string(), MessageState::ERROR, false).await;
-                        return Ok(())
-                    }
-                }
-            },
-            None => EMPTY_VALUE.into()
-        };
-
-        let map = osu::get_osu_instance().beatmap().map_id(score.map_id).await.expect("Beatmap exists");
-        embed = embeds::score_embed_from_score(&score, &map, reason).await?;
-        mode = score.mode;
-        parameters = format!("{}:{}:{}:{}:{}", "scoreid".to_string(), score.id.to_string(), map.map_id, requesting_user, identifier);
-        db::insert_score(unwrapped_score_id.to_string()).await?;
-
-    }
-    else if scorefile.is_some() {
-        let bytes = scorefile.unwrap().download().await?;
-        let replay = match osu_db::Replay::from_bytes(&bytes) {
-            Ok(replay) => replay,
-            Err(_) => {
-                embeds::single_text_response(&ctx, "Replay could not be parsed", MessageState::ERROR, false).await;
-                return Ok(());
-            },
-        };
-        let default_checksum = "".to_string();
-        let replay_checksum = replay.replay_hash.as_ref().unwrap_or(&default_checksum);
-        if db::has_score(replay_checksum.clone()).await? {
-            embeds::single_text_response(&ctx, "Score file has already been requested", MessageState::WARN, false).await;
-            return Ok(());
-        }
-        let map: rosu::BeatmapExtended = match osu::get_beatmap_from_checksum(&replay.beatmap_hash).await {
-            Some(map) => map,
-            None => {
-                embeds::single_text_response(&ctx, "Cannot find map related to the replay", MessageState::WARN, false).await;
-                return Ok(());
-            },
-        };
-
-        let identifier = match skin {
-            Some(identifier) => {
-                let player = match osu::get_osu_instance().user(replay.player_name.as_ref().unwrap()).await {
-                    Ok(player) => player,
-                    Err(_) => {
-                        embeds::single_text_response(&ctx, &format!("Could not find player ``{}``", replay.player_name.unwrap()).to_string(), MessageState::ERROR, false).await;
-                        return Ok(())
-                    }
-                };
-                match user::Entity::find().filter(user::Column::OsuId.eq(player.user_id as i64)).one(&db::get_db()).await? {
-                    Some(user) => {
-                        match db::get_skin_by_identifier(user, identifier.clone()).await? {
-                            Some(_) => identifier,
-                            None => {
-                                embeds::single_text_response(&ctx, &format!("Skin with that name {} does not exist", identifier).to_string(), MessageState::ERROR, false).await;
-                                return Ok(())
-                            }
-                        }
-                    },
-                    None => {
-                        embeds::single_text_response(&ctx, &format!("Skin with that name {} does not exist", identifier).to_string(), MessageState::ERROR, false).await;
-                        return Ok(())
-                    }
-                }
-            },
-            None => EMPTY_VALUE.into()
-        };
-        danser::attach_replay(&map.checksum.as_ref().unwrap(), replay_checksum, &bytes).await.unwrap();
-        embed = embeds::score_embed_from_replay_file(&replay, &map, reason).await?;
-        mode = rosu::GameMode::from(replay.mode.raw());
-        parameters = format!("{}:{}:{}:{}:{}", "replayfile".to_string(), replay_checksum.clone(), map.map_id, requesting_user, identifier);
-        db::insert_score(replay_checksum.clone()).await?;
+    let checked = if let Some(score_id) = scoreid {
+        check_score_id(score_id, reason.clone(), skin).await?
+    }
+    else if let Some(scorefile) = scorefile {
+        check_score_file(scorefile, reason.clone(), skin).await?
     }
     else {
         embeds::single_text_response(&ctx, "Please define scoreid or scorefile", MessageState::WARN, false).await;
         return Ok(());
+    };
+
+    match checked {
+        Ok(checked) => {
+            post_suggestion(ctx.serenity_context(), ctx.author(), checked, reason, false).await?;
+            embeds::single_text_response(&ctx, "Score has been requested!", MessageState::INFO, false).await;
+        },
+        Err(Refusal(message, state)) => embeds::single_text_response(&ctx, &message, state, false).await,
+    }
+    Ok(())
+}
+
+/// Suggest a score on behalf of `author` outside of `/suggest score`, running
+/// the same limits and checks.
+pub async fn suggest_score_id(ctx: &serenity::Context, author: &serenity::User, score_id: u64) -> Result<Result<(), Refusal>, Error> {
+    suggest_outside_command(ctx, author, check_score_id(score_id, None, None)).await
+}
+
+/// Button that suggests the score through `suggest_score_id`, disabled once it was requested.
+pub fn suggest_score_button(score_id: u64, already_requested: bool) -> CreateButton {
+    CreateButton::new(format!("suggestScore:{}", score_id))
+        .label(if already_requested { "Already suggested" } else { "Suggest this score" })
+        .emoji(ReactionType::Unicode("📨".to_string()))
+        .style(serenity::ButtonStyle::Primary)
+        .disabled(already_requested)
+}
+
+/// Like `suggest_score_id`, for a replay file posted somewhere else.
+pub async fn suggest_score_file(ctx: &serenity::Context, author: &serenity::User, scorefile: serenity::Attachment) -> Result<Result<(), Refusal>, Error> {
+    suggest_outside_command(ctx, author, check_score_file(scorefile, None, None)).await
+}
+
+async fn suggest_outside_command(
+    ctx: &serenity::Context,
+    author: &serenity::User,
+    check: impl Future<Output = Result<Result<CheckedSuggestion, Refusal>, Error>>,
+) -> Result<Result<(), Refusal>, Error> {
+    // The check marks the score as requested, so it may only run once the limits allow it.
+    if let Some(message) = rate_limit_message(author.id.get() as i64).await? {
+        return Ok(Err(Refusal::warn(message)));
+    }
+    match check.await? {
+        Ok(checked) => {
+            post_suggestion(ctx, author, checked, None, false).await?;
+            Ok(Ok(()))
+        },
+        Err(refusal) => Ok(Err(refusal)),
+    }
+}
+
+/// Suggest a play found by the player tracker. It is posted in the name of the
+/// bot, marked as auto-detected, and skips the per-user limits.
+pub async fn suggest_auto_detected(ctx: &serenity::Context, score_id: u64) -> Result<Result<(), Refusal>, Error> {
+    let bot: serenity::User = ctx.cache.current_user().clone().into();
+    match check_score_id(score_id, None, None).await? {
+        Ok(checked) => {
+            post_suggestion(ctx, &bot, checked, None, true).await?;
+            Ok(Ok(()))
+        },
+        Err(refusal) => Ok(Err(refusal)),
+    }
+}
+
+async fn check_score_id(score_id: u64, reason: Option<String>, skin: Option<String>) -> Result<Result<CheckedSuggestion, Refusal>, Error> {
+    if db::has_score(score_id.to_string()).await? {
+        return Ok(Err(Refusal::warn(format!("Score {} has already been requested", score_id))));
+    }
+    let score: rosu::Score = match osu::get_osu_instance().score(score_id).await {
+        Ok(score) => score,
+        Err(_) => return Ok(Err(Refusal::error(format!("Score with id {} does not exist", score_id)))),
+    };
+
+    if !score.has_replay {
+        return Ok(Err(Refusal::error("Score has no replay to download. Please provide the replay file")));
+    }
+
+    let identifier = match check_skin(score.user_id, skin).await? {
+        Ok(identifier) => identifier,
+        Err(refusal) => return Ok(Err(refusal)),
+    };
+
+    let map = osu::get_osu_instance().beatmap().map_id(score.map_id).await?;
+    let embed = embeds::score_embed_from_score(&score, &map, reason).await?;
+    let row = suggestion::ActiveModel {
+        kind: Set(JobKind::ScoreId.as_str().to_string()),
+        reference: Set(score.id.to_string()),
+        map_id: Set(map.map_id as i64),
+        osu_user_id: Set(score.user_id as i64),
+        player_name: Set(score.user.as_ref().map(|user| user.username.to_string())),
+        map_title: Set(Some(osu::formatter::map_title(&map))),
+        pp: Set(score.pp.map(f64::from)),
+        ..Default::default()
+    };
+    db::insert_score(score_id.to_string()).await?;
+
+    Ok(Ok(CheckedSuggestion { embed, mode: score.mode, row, identifier }))
+}
+
+async fn check_score_file(scorefile: serenity::Attachment, reason: Option<String>, skin: Option<String>) -> Result<Result<CheckedSuggestion, Refusal>, Error> {
+    let bytes = scorefile.download().await?;
+    let replay = match osu_db::Replay::from_bytes(&bytes) {
+        Ok(replay) => replay,
+        Err(_) => return Ok(Err(Refusal::error("Replay could not be parsed"))),
+    };
+    let default_checksum = "".to_string();
+    let replay_checksum = replay.replay_hash.as_ref().unwrap_or(&default_checksum);
+    if db::has_score(replay_checksum.clone()).await? {
+        return Ok(Err(Refusal::warn("Score file has already been requested")));
     }
+    let map: rosu::BeatmapExtended = match osu::get_beatmap_from_checksum(&replay.beatmap_hash).await {
+        Some(map) => map,
+        None => return Ok(Err(Refusal::warn("Cannot find map related to the replay"))),
+    };
 
+    let player = match osu::get_osu_instance().user(replay.player_name.as_ref().unwrap()).await {
+        Ok(player) => player,
+        Err(_) => return Ok(Err(Refusal::error(format!("Could not find player ``{}``", replay.player_name.unwrap())))),
+    };
+    let identifier = match check_skin(player.user_id, skin).await? {
+        Ok(identifier) => identifier,
+        Err(refusal) => return Ok(Err(refusal)),
+    };
+    danser::attach_replay(&map.checksum.as_ref().unwrap(), replay_checksum, &bytes).await.unwrap();
+    let embed = embeds::score_embed_from_replay_file(&replay, &map, reason).await?;
+    let pp = osu::pp_calculator::calculate_score_by_replay(&replay, &map).await.ok().map(|result| f64::from(result.pp));
+    let row = suggestion::ActiveModel {
+        kind: Set(JobKind::ReplayFile.as_str().to_string()),
+        reference: Set(replay_checksum.clone()),
+        map_id: Set(map.map_id as i64),
+        osu_user_id: Set(player.user_id as i64),
+        player_name: Set(Some(player.username.to_string())),
+        map_title: Set(Some(osu::formatter::map_title(&map))),
+        pp: Set(pp),
+        ..Default::default()
+    };
+    db::insert_score(replay_checksum.clone()).await?;
+
+    Ok(Ok(CheckedSuggestion { embed, mode: rosu::GameMode::from(replay.mode.raw()), row, identifier }))
+}
+
+/// Skins can only be picked from the ones the player set themselves.
+async fn check_skin(osu_user_id: u32, skin: Option<String>) -> Result<Result<Option<String>, Refusal>, Error> {
+    let Some(identifier) = skin else {
+        return Ok(Ok(None));
+    };
+    let user = db::get_linked_user_by_osu_id(osu_user_id as i64).await?;
+    let skin = match user {
+        Some(user) => db::get_skin_by_identifier(user, identifier.clone()).await?,
+        None => None,
+    };
+    match skin {
+        Some(_) => Ok(Ok(Some(identifier))),
+        None => Ok(Err(Refusal::error(format!("Skin with that name {} does not exist", identifier)))),
+    }
+}
+
+async fn post_suggestion(ctx: &serenity::Context, author: &serenity::User, checked: CheckedSuggestion, reason: Option<String>, auto_detected: bool) -> Result<(), Error> {
+    let CheckedSuggestion { embed, mode, row, identifier } = checked;
+
+    // Buttons only carry the row id; everything else is read back from the row.
+    let suggestion = suggestion::ActiveModel {
+        status: Set(db::SUGGESTION_PENDING.to_string()),
+        mode: Set(mode as i64),
+        requested_by: Set(author.id.get() as i64),
+        reason: Set(reason),
+        skin_identifier: Set(identifier),
+        auto_detected: Set(auto_detected),
+        created_at: Set(time::OffsetDateTime::now_utc().unix_timestamp()),
+        ..row
+    }.insert(&db::get_db()).await?;
+    let parameters = suggestion.id;
     let mut buttons: Vec<CreateButton> = vec![];
     
     if mode == rosu::GameMode::Osu {
@@ -140,6 +384,22 @@ pub async fn score(
         .emoji(ReactionType::Unicode("✅".to_string()))
         .style(serenity::ButtonStyle::Success);
         buttons.push(approve_button);
+
+        if defaults::ARCHIVE_PATH.is_some() {
+            let archive_id = format!("approveWithArchive:{}", parameters);
+            let archive_button = serenity::CreateButton::new(archive_id)
+            .label("Approve to archive")
+            .emoji(ReactionType::Unicode("🗄️".to_string()))
+            .style(serenity::ButtonStyle::Secondary);
+            buttons.push(archive_button);
+        }
+
+        let discord_id = format!("approveWithDiscord:{}", parameters);
+        let discord_button = serenity::CreateButton::new(discord_id)
+        .label("Approve to Discord")
+        .emoji(ReactionType::Unicode("💬".to_string()))
+        .style(serenity::ButtonStyle::Secondary);
+        buttons.push(discord_button);
     }
     else {
         let approve_id = format!("approveNoUpload:{}", parameters);
@@ -158,10 +418,31 @@ pub async fn score(
 
     buttons.push(decline_button);
 
-    let suggestion = serenity::CreateMessage::new()
-            .embed(embed.footer(serenity::CreateEmbedFooter::new(format!("Requested by @{}", ctx.author().name))))
-            .components(vec![serenity::CreateActionRow::Buttons(buttons)]);
-    defaults::SUGGESTIONS_CHANNEL.send_message(ctx, suggestion).await?;
-    embeds::single_text_response(&ctx, "Score has been requested!", MessageState::INFO, false).await;
+    let mut components = vec![serenity::CreateActionRow::Buttons(buttons)];
+    if !defaults::DECLINE_REASONS.is_empty() {
+        // Select menus take at most 25 options of up to 100 characters each.
+        let options = defaults::DECLINE_REASONS.iter()
+            .take(25)
+            .map(|reason| {
+                let reason: String = reason.chars().take(100).collect();
+                serenity::CreateSelectMenuOption::new(reason.clone(), reason)
+            })
+            .collect();
+        let decline_reasons = serenity::CreateSelectMenu::new(format!("declineReason:{}", parameters), serenity::CreateSelectMenuKind::String { options })
+            .placeholder("Decline with a canned reason");
+        components.push(serenity::CreateActionRow::SelectMenu(decline_reasons));
+    }
+
+    let footer = if auto_detected { "Auto-detected new top play".to_string() } else { format!("Requested by @{}", author.name) };
+    let suggestion_message = serenity::CreateMessage::new()
+            .embed(embed.footer(serenity::CreateEmbedFooter::new(footer)))
+            .components(components);
+    let message = defaults::SUGGESTIONS_CHANNEL.send_message(ctx, suggestion_message).await?;
+    suggestion::ActiveModel {
+        id: Set(suggestion.id),
+        channel_id: Set(Some(message.channel_id.get() as i64)),
+        message_id: Set(Some(message.id.get() as i64)),
+        ..Default::default()
+    }.update(&db::get_db()).await?;
     Ok(())
 }
\ No newline at end of file
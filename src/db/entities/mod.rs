@@ -1,4 +1,11 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 2.0
+pub mod announced_video;
+pub mod feed_subscription;
+pub mod render_job;
 pub mod score;
 pub mod skin;
+pub mod suggestion;
+pub mod suggestion_limit;
+pub mod task_state;
+pub mod upload;
 pub mod user;
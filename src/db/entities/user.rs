@@ -10,6 +10,8 @@ pub struct Model {
     pub osu_id: i64,
     pub discord_id: i64,
     pub is_blacklisted: bool,
+    pub osu_username: Option<String>,
+    pub linked_at: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
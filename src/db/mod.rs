@@ -2,12 +2,17 @@ pub mod entities;
 
 use std::sync::OnceLock;
 
-use sea_orm::{ActiveModelTrait, ActiveValue::Set, ColumnTrait, Database, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, ColumnTrait, Database, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Select, sea_query::OnConflict};
 
-use crate::{Error, db::entities::{score, skin, user}};
+use crate::{Error, db::entities::{announced_video, feed_subscription, score, skin, suggestion, suggestion_limit, task_state, upload, user}};
 
 static DB: OnceLock<DatabaseConnection> = OnceLock::new();
 
+pub const SUGGESTION_PENDING: &str = "pending";
+pub const SUGGESTION_APPROVED: &str = "approved";
+pub const SUGGESTION_DECLINED: &str = "declined";
+pub const SUGGESTION_UPLOADED: &str = "uploaded";
+
 pub async fn init_db() -> Result<(), Error> {
     let db_connection = Database::connect(std::env::var("DATABASE_URL").expect("missing DATABASE_URL")).await?;
 
@@ -25,13 +30,13 @@ pub async fn get_user_by_discord_id(discord_id: i64) -> Result<Option<user::Mode
     Ok(user::Entity::find().filter(user::Column::DiscordId.eq(discord_id)).one(&get_db()).await?)
 }
 
-pub async fn get_user_by_discord_id_or_create(discord_id: i64, osu_id: i32) -> Result<user::Model, Error> {
+pub async fn get_user_by_discord_id_or_create(discord_id: i64) -> Result<user::Model, Error> {
     let user = match get_user_by_discord_id(discord_id).await? {
         Some(user) => user,
         None => {
             user::ActiveModel {
                 discord_id: Set(discord_id),
-                osu_id: Set(osu_id as i64),
+                osu_id: Set(0),
                 ..Default::default()
             }.insert(&get_db()).await?
         }
@@ -39,6 +44,47 @@ pub async fn get_user_by_discord_id_or_create(discord_id: i64, osu_id: i32) -> R
     Ok(user)
 }
 
+/// The user behind a Discord account, if they linked an osu! account via `/link`.
+pub async fn get_linked_user(discord_id: i64) -> Result<Option<user::Model>, Error> {
+    Ok(user::Entity::find()
+        .filter(user::Column::DiscordId.eq(discord_id))
+        .filter(user::Column::LinkedAt.is_not_null())
+        .one(&get_db()).await?)
+}
+
+pub async fn get_linked_user_by_osu_id(osu_id: i64) -> Result<Option<user::Model>, Error> {
+    Ok(user::Entity::find()
+        .filter(user::Column::OsuId.eq(osu_id))
+        .filter(user::Column::LinkedAt.is_not_null())
+        .one(&get_db()).await?)
+}
+
+pub async fn linked_users() -> Result<Vec<user::Model>, Error> {
+    Ok(user::Entity::find().filter(user::Column::LinkedAt.is_not_null()).all(&get_db()).await?)
+}
+
+pub async fn link_user(discord_id: i64, osu_id: i64, osu_username: String) -> Result<(), Error> {
+    let mut user: user::ActiveModel = get_user_by_discord_id_or_create(discord_id).await?.into();
+    user.osu_id = Set(osu_id);
+    user.osu_username = Set(Some(osu_username));
+    user.linked_at = Set(Some(time::OffsetDateTime::now_utc().unix_timestamp()));
+    user.update(&get_db()).await?;
+    Ok(())
+}
+
+/// Returns false if the Discord account wasn't linked.
+pub async fn unlink_user(discord_id: i64) -> Result<bool, Error> {
+    let Some(user) = get_linked_user(discord_id).await? else {
+        return Ok(false);
+    };
+    let mut user: user::ActiveModel = user.into();
+    user.osu_id = Set(0);
+    user.osu_username = Set(None);
+    user.linked_at = Set(None);
+    user.update(&get_db()).await?;
+    Ok(true)
+}
+
 pub async fn get_skin_by_identifier(user: user::Model, identifier: String) -> Result<Option<skin::Model>, Error> {
     Ok(skin::Entity::find()
         .filter(skin::Column::User.eq::<i64>(user.id as i64))
@@ -56,3 +102,251 @@ pub async fn insert_score(score_reference: String) -> Result<(), Error> {
     }.insert(&get_db()).await?;
     Ok(())
 }
+
+pub async fn feed_subscriptions() -> Result<Vec<feed_subscription::Model>, Error> {
+    Ok(feed_subscription::Entity::find().order_by_asc(feed_subscription::Column::Id).all(&get_db()).await?)
+}
+
+pub async fn add_feed_subscription(youtube_channel_id: String, discord_channel_id: i64, template: Option<String>, role_id: Option<i64>) -> Result<feed_subscription::Model, Error> {
+    Ok(feed_subscription::ActiveModel {
+        youtube_channel_id: Set(youtube_channel_id),
+        discord_channel_id: Set(discord_channel_id),
+        template: Set(template),
+        role_id: Set(role_id),
+        created_at: Set(time::OffsetDateTime::now_utc().unix_timestamp()),
+        ..Default::default()
+    }.insert(&get_db()).await?)
+}
+
+/// Returns false if there is no subscription with that id.
+pub async fn remove_feed_subscription(id: i64) -> Result<bool, Error> {
+    let removed = feed_subscription::Entity::delete_by_id(id).exec(&get_db()).await?.rows_affected > 0;
+    announced_video::Entity::delete_many().filter(announced_video::Column::Subscription.eq(id)).exec(&get_db()).await?;
+    task_state::Entity::delete_by_id(feed_primed_key(id)).exec(&get_db()).await?;
+    Ok(removed)
+}
+
+/// Turn the feed configured through the environment into a subscription,
+/// keeping the videos it already announced.
+pub async fn import_feed_subscription(youtube_channel_id: String, discord_channel_id: i64) -> Result<feed_subscription::Model, Error> {
+    let subscription = add_feed_subscription(youtube_channel_id, discord_channel_id, None, None).await?;
+    announced_video::Entity::update_many()
+        .col_expr(announced_video::Column::Subscription, subscription.id.into())
+        .filter(announced_video::Column::Subscription.eq(0))
+        .exec(&get_db()).await?;
+    Ok(subscription)
+}
+
+/// Which of `video_ids` the subscription already announced.
+pub async fn announced_video_ids(subscription: i64, video_ids: &[String]) -> Result<Vec<String>, Error> {
+    Ok(announced_video::Entity::find()
+        .filter(announced_video::Column::Subscription.eq(subscription))
+        .filter(announced_video::Column::VideoId.is_in(video_ids.iter().cloned()))
+        .all(&get_db()).await?
+        .into_iter().map(|video| video.video_id).collect())
+}
+
+/// `task_state` key set once the subscription's feed has been stored for the first time.
+pub fn feed_primed_key(subscription: i64) -> String {
+    format!("feed_primed:{}", subscription)
+}
+
+pub async fn insert_announced_video(subscription: i64, video_id: String) -> Result<(), Error> {
+    announced_video::Entity::insert(announced_video::ActiveModel {
+        subscription: Set(subscription),
+        video_id: Set(video_id),
+        announced_at: Set(time::OffsetDateTime::now_utc().unix_timestamp()),
+    })
+    .on_conflict_do_nothing_on([announced_video::Column::Subscription, announced_video::Column::VideoId])
+    .exec(&get_db()).await?;
+    Ok(())
+}
+
+pub async fn find_upload_by_video_id(video_id: &str) -> Result<Option<upload::Model>, Error> {
+    Ok(upload::Entity::find().filter(upload::Column::VideoId.eq(video_id)).one(&get_db()).await?)
+}
+
+/// A video the bot uploaded to YouTube, as stored on its `upload` row.
+pub struct YoutubeVideo {
+    pub video_id: String,
+    pub title: String,
+    pub description: String,
+}
+
+/// Find a video the bot uploaded, either by its YouTube id or by the score id /
+/// replay hash it was rendered from (latest upload wins).
+pub async fn find_youtube_video(video_or_reference: &str) -> Result<Option<YoutubeVideo>, Error> {
+    let upload = match find_upload_by_video_id(video_or_reference).await? {
+        Some(upload) => Some(upload),
+        None => upload::Entity::find()
+            .filter(upload::Column::Reference.eq(video_or_reference))
+            .filter(upload::Column::VideoId.is_not_null())
+            .order_by_desc(upload::Column::UploadedAt)
+            .one(&get_db()).await?,
+    };
+    Ok(upload.and_then(|upload| Some(YoutubeVideo {
+        video_id: upload.video_id?,
+        title: upload.title.unwrap_or_default(),
+        description: upload.description.unwrap_or_default(),
+    })))
+}
+
+/// Keep the stored title and description in line with edits made through the bot.
+pub async fn update_youtube_video(video_id: &str, title: Option<String>, description: Option<String>) -> Result<(), Error> {
+    let mut update = upload::Entity::update_many().filter(upload::Column::VideoId.eq(video_id));
+    if title.is_none() && description.is_none() {
+        return Ok(());
+    }
+    if let Some(title) = title {
+        update = update.col_expr(upload::Column::Title, Some(title).into());
+    }
+    if let Some(description) = description {
+        update = update.col_expr(upload::Column::Description, Some(description).into());
+    }
+    update.exec(&get_db()).await?;
+    Ok(())
+}
+
+pub async fn get_suggestion(id: i64) -> Result<Option<suggestion::Model>, Error> {
+    Ok(suggestion::Entity::find_by_id(id).one(&get_db()).await?)
+}
+
+/// Move a suggestion to `status`. `handled_by` is the staff member who approved
+/// or declined it; the upload step leaves it as is.
+pub async fn set_suggestion_status(id: i64, status: &str, handled_by: Option<i64>, decline_reason: Option<String>) -> Result<(), Error> {
+    let mut suggestion = suggestion::ActiveModel {
+        id: Set(id),
+        status: Set(status.to_string()),
+        ..Default::default()
+    };
+    if decline_reason.is_some() {
+        suggestion.decline_reason = Set(decline_reason);
+    }
+    if let Some(handled_by) = handled_by {
+        suggestion.handled_by = Set(Some(handled_by));
+        suggestion.handled_at = Set(Some(time::OffsetDateTime::now_utc().unix_timestamp()));
+    }
+    suggestion.update(&get_db()).await?;
+    Ok(())
+}
+
+/// Move a pending suggestion to `status` for the staff member `handled_by`.
+/// `false` if it was handled by someone else in the meantime.
+pub async fn claim_suggestion(id: i64, status: &str, handled_by: i64, decline_reason: Option<String>) -> Result<bool, Error> {
+    let result = suggestion::Entity::update_many()
+        .col_expr(suggestion::Column::Status, status.into())
+        .col_expr(suggestion::Column::HandledBy, Some(handled_by).into())
+        .col_expr(suggestion::Column::HandledAt, Some(time::OffsetDateTime::now_utc().unix_timestamp()).into())
+        .col_expr(suggestion::Column::DeclineReason, decline_reason.into())
+        .filter(suggestion::Column::Id.eq(id))
+        .filter(suggestion::Column::Status.eq(SUGGESTION_PENDING))
+        .exec(&get_db()).await?;
+    Ok(result.rows_affected == 1)
+}
+
+/// Undo `claim_suggestion` for an approval that failed before its render was queued.
+pub async fn release_suggestion(id: i64) -> Result<(), Error> {
+    suggestion::Entity::update_many()
+        .col_expr(suggestion::Column::Status, SUGGESTION_PENDING.into())
+        .col_expr(suggestion::Column::HandledBy, Option::<i64>::None.into())
+        .col_expr(suggestion::Column::HandledAt, Option::<i64>::None.into())
+        .filter(suggestion::Column::Id.eq(id))
+        .filter(suggestion::Column::Status.eq(SUGGESTION_APPROVED))
+        .exec(&get_db()).await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SuggestionFilter {
+    pub osu_user_id: Option<i64>,
+    pub mode: Option<i64>,
+    pub older_than_days: Option<u32>,
+}
+
+impl SuggestionFilter {
+    fn apply(&self, query: Select<suggestion::Entity>) -> Select<suggestion::Entity> {
+        let mut query = query.filter(suggestion::Column::Status.eq(SUGGESTION_PENDING));
+        if let Some(osu_user_id) = self.osu_user_id {
+            query = query.filter(suggestion::Column::OsuUserId.eq(osu_user_id));
+        }
+        if let Some(mode) = self.mode {
+            query = query.filter(suggestion::Column::Mode.eq(mode));
+        }
+        if let Some(days) = self.older_than_days {
+            let cutoff = time::OffsetDateTime::now_utc().unix_timestamp() - days as i64 * 24 * 60 * 60;
+            query = query.filter(suggestion::Column::CreatedAt.lte(cutoff));
+        }
+        query
+    }
+}
+
+/// One page of pending suggestions, oldest first, plus the number of pages.
+pub async fn pending_suggestions(filter: &SuggestionFilter, page: u64, page_size: u64) -> Result<(Vec<suggestion::Model>, u64), Error> {
+    let db = get_db();
+    let paginator = filter.apply(suggestion::Entity::find())
+        .order_by_asc(suggestion::Column::Id)
+        .paginate(&db, page_size);
+    let pages = paginator.num_pages().await?;
+    Ok((paginator.fetch_page(page).await?, pages))
+}
+
+/// The suggestion limits live in a single row; `None` means the limit is off.
+pub async fn get_suggestion_limits() -> Result<suggestion_limit::Model, Error> {
+    Ok(suggestion_limit::Entity::find_by_id(1).one(&get_db()).await?.unwrap_or(suggestion_limit::Model {
+        id: 1,
+        max_pending: None,
+        cooldown_seconds: None,
+        daily_cap: None,
+    }))
+}
+
+pub async fn set_suggestion_limits(limits: suggestion_limit::Model) -> Result<(), Error> {
+    let limits: suggestion_limit::ActiveModel = limits.into();
+    suggestion_limit::Entity::insert(limits.reset_all())
+        .on_conflict(
+            OnConflict::column(suggestion_limit::Column::Id)
+                .update_columns([suggestion_limit::Column::MaxPending, suggestion_limit::Column::CooldownSeconds, suggestion_limit::Column::DailyCap])
+                .to_owned()
+        )
+        .exec(&get_db()).await?;
+    Ok(())
+}
+
+/// Values background tasks keep across restarts, like the last poll time.
+pub async fn get_task_state(key: &str) -> Result<Option<i64>, Error> {
+    Ok(task_state::Entity::find_by_id(key.to_string()).one(&get_db()).await?.map(|state| state.value))
+}
+
+pub async fn set_task_state(key: &str, value: i64) -> Result<(), Error> {
+    let state = task_state::ActiveModel {
+        key: Set(key.to_string()),
+        value: Set(value),
+    };
+    task_state::Entity::insert(state)
+        .on_conflict(
+            OnConflict::column(task_state::Column::Key)
+                .update_column(task_state::Column::Value)
+                .to_owned()
+        )
+        .exec(&get_db()).await?;
+    Ok(())
+}
+
+pub async fn count_pending_suggestions_by(discord_id: i64) -> Result<u64, Error> {
+    Ok(suggestion::Entity::find()
+        .filter(suggestion::Column::RequestedBy.eq(discord_id))
+        .filter(suggestion::Column::Status.eq(SUGGESTION_PENDING))
+        .count(&get_db()).await?)
+}
+
+/// Creation times of the suggestions a user made since `since`, oldest first.
+pub async fn suggestion_times_by(discord_id: i64, since: i64) -> Result<Vec<i64>, Error> {
+    Ok(suggestion::Entity::find()
+        .select_only()
+        .column(suggestion::Column::CreatedAt)
+        .filter(suggestion::Column::RequestedBy.eq(discord_id))
+        .filter(suggestion::Column::CreatedAt.gte(since))
+        .order_by_asc(suggestion::Column::CreatedAt)
+        .into_tuple()
+        .all(&get_db()).await?)
+}
@@ -27,10 +27,144 @@ pub static SUGGESTIONS_CHANNEL: LazyLock<serenity::ChannelId> = LazyLock::new(||
     serenity::ChannelId::new(id)
 });
 
-pub static NEW_VIDEOS_CHANNEL: LazyLock<serenity::ChannelId> = LazyLock::new(|| {
-    let id: u64 = std::env::var("OSC_BOT_NEW_VIDEOS_CHANNEL")
-        .expect("OSC_BOT_NEW_VIDEOS_CHANNEL not set")
-        .parse()
-        .expect("OSC_BOT_NEW_VIDEOS_CHANNEL must be u64");
-    serenity::ChannelId::new(id)
+/// Feed imported as the first subscription while none exist. Later feeds are
+/// managed with `/admin feed`.
+pub static NEW_VIDEOS_CHANNEL: LazyLock<Option<serenity::ChannelId>> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_NEW_VIDEOS_CHANNEL")
+        .ok()
+        .filter(|id| !id.is_empty())
+        .map(|id| serenity::ChannelId::new(id.parse().expect("OSC_BOT_NEW_VIDEOS_CHANNEL must be u64")))
+});
+
+pub static YOUTUBE_CHANNEL_ID: LazyLock<Option<String>> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_YOUTUBE_CHANNEL_ID").ok().filter(|id| !id.is_empty())
+});
+
+pub static RENDER_WORKERS: LazyLock<usize> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_RENDER_WORKERS")
+        .map(|workers| workers.parse().expect("OSC_BOT_RENDER_WORKERS must be usize"))
+        .unwrap_or(1)
+});
+
+pub static ARCHIVE_PATH: LazyLock<Option<std::path::PathBuf>> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_ARCHIVE_PATH").ok().map(std::path::PathBuf::from)
+});
+
+pub static YOUTUBE_ADMIN: LazyLock<Option<serenity::UserId>> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_YOUTUBE_ADMIN")
+        .ok()
+        .filter(|id| !id.is_empty())
+        .map(|id| serenity::UserId::new(id.parse().expect("OSC_BOT_YOUTUBE_ADMIN must be u64")))
+});
+
+pub static YOUTUBE_PRIVACY: LazyLock<crate::apis::youtube::Privacy> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_YOUTUBE_PRIVACY")
+        .ok()
+        .filter(|privacy| !privacy.is_empty())
+        .map(|privacy| crate::apis::youtube::Privacy::parse(&privacy).expect("OSC_BOT_YOUTUBE_PRIVACY must be public, unlisted or private"))
+        .unwrap_or(crate::apis::youtube::Privacy::Unlisted)
+});
+
+pub static YOUTUBE_PLAYLISTS: LazyLock<Vec<String>> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_YOUTUBE_PLAYLISTS")
+        .map(|playlists| crate::apis::youtube::parse_playlists(&playlists))
+        .unwrap_or_default()
+});
+
+/// Channels in which posted `.osr` files get a summary. Empty leaves replays alone.
+pub static REPLAY_CHANNELS: LazyLock<Vec<serenity::ChannelId>> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_REPLAY_CHANNELS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| serenity::ChannelId::new(id.parse().expect("OSC_BOT_REPLAY_CHANNELS must be comma separated u64")))
+        .collect()
+});
+
+/// Canned reasons offered below each suggestion, separated by `;`.
+pub static DECLINE_REASONS: LazyLock<Vec<String>> = LazyLock::new(|| {
+    let reasons = std::env::var("OSC_BOT_DECLINE_REASONS")
+        .unwrap_or("Replay too short;Not Swiss;Not special enough for an upload;Already uploaded".to_string());
+    reasons.split(';').map(str::trim).filter(|reason| !reason.is_empty()).map(String::from).collect()
+});
+
+/// Redirect URI registered for the osu! OAuth client. Unset disables `/link`.
+pub static LINK_REDIRECT_URI: LazyLock<Option<url::Url>> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_OSU_REDIRECT_URI")
+        .ok()
+        .filter(|uri| !uri.is_empty())
+        .map(|uri| url::Url::parse(&uri).expect("OSC_BOT_OSU_REDIRECT_URI must be a URL"))
+});
+
+/// Address the OAuth callback listener binds to; the redirect URI has to reach it.
+pub static LINK_LISTEN: LazyLock<String> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_LINK_LISTEN")
+        .ok()
+        .filter(|address| !address.is_empty())
+        .unwrap_or("0.0.0.0:7270".to_string())
+});
+
+/// Roles synced from the linked osu! account's rank, e.g. `global:100=<role>;country:10=<role>;pp:8000=<role>`.
+pub static RANK_ROLES: LazyLock<Vec<crate::events::background_tasks::rank_roles::RankRole>> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_RANK_ROLES")
+        .map(|rules| crate::events::background_tasks::rank_roles::parse_rank_roles(&rules).expect("OSC_BOT_RANK_ROLES is invalid"))
+        .unwrap_or_default()
+});
+
+/// Mode whose statistics the rank roles are based on (default osu).
+pub static RANK_ROLES_MODE: LazyLock<rosu_v2::prelude::GameMode> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_RANK_ROLES_MODE")
+        .ok()
+        .filter(|mode| !mode.is_empty())
+        .map(|mode| crate::osu::links::parse_mode(&mode).expect("OSC_BOT_RANK_ROLES_MODE must be osu, taiko, fruits or mania"))
+        .unwrap_or(rosu_v2::prelude::GameMode::Osu)
+});
+
+/// Poll linked members (and the country leaderboard, if set) for new top plays to suggest.
+pub static TRACK_PLAYERS: LazyLock<bool> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_TRACK_PLAYERS").map(|track| track == "true").unwrap_or(false)
+});
+
+/// Country code whose leaderboard top `TRACK_COUNTRY_TOP` players are tracked too.
+pub static TRACK_COUNTRY: LazyLock<Option<String>> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_TRACK_COUNTRY").ok().filter(|country| !country.is_empty())
+});
+
+pub static TRACK_COUNTRY_TOP: LazyLock<u32> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_TRACK_COUNTRY_TOP")
+        .map(|top| top.parse().expect("OSC_BOT_TRACK_COUNTRY_TOP must be u32"))
+        .unwrap_or(50)
+});
+
+/// Tracked plays below this pp are not suggested.
+pub static TRACK_MIN_PP: LazyLock<f32> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_TRACK_MIN_PP")
+        .map(|pp| pp.parse().expect("OSC_BOT_TRACK_MIN_PP must be a number"))
+        .unwrap_or(0.0)
+});
+
+/// Only plays within this many of the player's top plays are suggested (1-100).
+pub static TRACK_TOP_POSITION: LazyLock<usize> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_TRACK_TOP_POSITION")
+        .map(|position| position.parse().expect("OSC_BOT_TRACK_TOP_POSITION must be usize"))
+        .unwrap_or(100)
+        .clamp(1, 100)
+});
+
+/// Channel that announces new country #1 scores of linked members. Unset disables it.
+/// Incomplete: only country firsts within a map's global top 100 are found.
+pub static COUNTRY_FIRSTS_CHANNEL: LazyLock<Option<serenity::ChannelId>> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_COUNTRY_FIRSTS_CHANNEL")
+        .ok()
+        .filter(|id| !id.is_empty())
+        .map(|id| serenity::ChannelId::new(id.parse().expect("OSC_BOT_COUNTRY_FIRSTS_CHANNEL must be u64")))
+});
+
+/// Country of the `COUNTRY_FIRSTS_CHANNEL` announcements.
+pub static COUNTRY_FIRSTS_COUNTRY: LazyLock<String> = LazyLock::new(|| {
+    std::env::var("OSC_BOT_COUNTRY_FIRSTS_COUNTRY")
+        .ok()
+        .filter(|country| !country.is_empty())
+        .unwrap_or("CH".to_string())
 });
@@ -1,5 +1,5 @@
 use poise::CreateReply;
-use poise::serenity_prelude::{self as serenity, CacheHttp, CreateAttachment, CreateEmbed, CreateInteractionResponseMessage};
+use poise::serenity_prelude::{self as serenity, CacheHttp, CreateActionRow, CreateAttachment, CreateEmbed, CreateInteractionResponseMessage, EditMessage};
 use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
 
 use crate::{Context, db};
@@ -12,6 +12,9 @@ pub struct ContextForFunctions<'a> {
     pub reply: Option<poise::ReplyHandle<'a>>,
     pub event_context: Option<&'a (dyn serenity::CacheHttp + Send + Sync)>,
     pub component: Option<&'a serenity::ComponentInteraction>,
+    /// Message to edit directly instead of going through an interaction, used by
+    /// render workers that outlive the interaction token.
+    pub message: Option<(serenity::ChannelId, serenity::MessageId)>,
 }
 
 impl<'a> ContextForFunctions<'a> {
@@ -32,12 +35,36 @@ impl<'a> ContextForFunctions<'a> {
     }
 
     pub async fn edit(&self, embed: CreateEmbed, attachments: Vec<CreateAttachment>) -> Result<(), Error> {
+        self.edit_message(embed, attachments, None).await
+    }
+
+    /// Like `edit`, but also replaces the buttons below the embed.
+    pub async fn edit_with_components(&self, embed: CreateEmbed, attachments: Vec<CreateAttachment>, components: Vec<CreateActionRow>) -> Result<(), Error> {
+        self.edit_message(embed, attachments, Some(components)).await
+    }
+
+    async fn edit_message(&self, embed: CreateEmbed, attachments: Vec<CreateAttachment>, components: Option<Vec<CreateActionRow>>) -> Result<(), Error> {
+        if let Some((channel_id, message_id)) = self.message {
+            let mut edit = EditMessage::default().embed(embed);
+            for attachment in attachments {
+                edit = edit.new_attachment(attachment);
+            }
+            if let Some(components) = components {
+                edit = edit.components(components);
+            }
+            channel_id.edit_message(self.event_context.unwrap().http(), message_id, edit).await?;
+            return Ok(());
+        }
+
         match self.command_context {
             Some(ctx) => {
                 let mut reply = CreateReply::default().embed(embed);
                 for attachment in attachments {
                     reply = reply.attachment(attachment);
                 }
+                if let Some(components) = components {
+                    reply = reply.components(components);
+                }
                 self.reply.as_ref().unwrap().edit(ctx, reply).await.unwrap()
             }
             None => {
@@ -45,6 +72,9 @@ impl<'a> ContextForFunctions<'a> {
                 for attachment in attachments {
                     reply = reply.new_attachment(attachment);
                 }
+                if let Some(components) = components {
+                    reply = reply.components(components);
+                }
                 self.component.unwrap().edit_response(self.event_context.unwrap().http(),
                 reply
             ).await.unwrap();
@@ -52,6 +82,19 @@ impl<'a> ContextForFunctions<'a> {
         }
         Ok(())
     }
+
+    /// Channel and message id of the response that `send` created.
+    pub async fn message_location(&self) -> Result<(serenity::ChannelId, serenity::MessageId), Error> {
+        if let Some(location) = self.message {
+            return Ok(location);
+        }
+
+        let message = match self.command_context {
+            Some(_) => self.reply.as_ref().unwrap().message().await?.into_owned(),
+            None => self.component.unwrap().get_response(self.event_context.unwrap().http()).await?,
+        };
+        Ok((message.channel_id, message.id))
+    }
 }
 
 #[derive(PartialEq)]
@@ -102,15 +145,17 @@ pub async fn user_has_replay_role(ctx: impl CacheHttp, user: &serenity::User) ->
 
 pub async fn global_check(ctx: Context<'_>) -> Result<bool, Error> {
     tracing::info!(user = ctx.author().display_name(), command = ctx.command().qualified_name , "User called a command");
-    let user_id = ctx.author().id;
-    let is_blacklisted = user::Entity::find()
-        .filter(user::Column::DiscordId.eq::<u64>(user_id.into()))
-        .filter(user::Column::IsBlacklisted.eq(true)).count(&db::get_db()).await? > 0;
-    if is_blacklisted {
+    if is_blacklisted(ctx.author().id).await? {
         single_text_response(&ctx, "You are blacklisted", MessageState::INFO, true).await;
         tracing::warn!(user = ctx.author().display_name(), "Blacklisted user tried to use features");
         return Ok(false)
     }
 
     Ok(true)
-}
\ No newline at end of file
+}
+
+pub async fn is_blacklisted(user_id: serenity::UserId) -> Result<bool, Error> {
+    Ok(user::Entity::find()
+        .filter(user::Column::DiscordId.eq::<u64>(user_id.into()))
+        .filter(user::Column::IsBlacklisted.eq(true)).count(&db::get_db()).await? > 0)
+}
@@ -1,7 +1,13 @@
 use rosu_v2::prelude as rosu;
-use poise::serenity_prelude::{self as serenity, Colour, CreateEmbed};
+use poise::serenity_prelude::{self as serenity, Colour, CreateEmbed, Mentionable};
 
 use crate::osu;
+use crate::osu::pp_calculator::{CalculateMapResponse, CalculateScoreResponse};
+use crate::apis::youtube::{self, StoredToken};
+use crate::db::entities::{feed_subscription, suggestion, suggestion_limit, upload};
+use crate::defaults::SERVER;
+use crate::generate::uploader::UploadOutcome;
+use crate::events::background_tasks::rank_roles::RoleChange;
 use crate::{Context, Error};
 use crate::discord_helper::MessageState;
 
@@ -37,14 +43,80 @@ pub async fn score_embed_from_replay_file(replay: &osu_db::Replay, map: &rosu::B
     let result = osu::pp_calculator::calculate_score_by_replay(replay, map).await?;
     let hits = format!("{}/{}/{}/{}", replay.count_300, replay.count_100, replay.count_50, replay.count_miss);
     let mods = osu::formatter::convert_osu_db_to_mod_array(replay.mods).join("");
-    score_embed(map, &user, Some(replay.online_score_id), replay.score, result.accuracy, hits, replay.max_combo as u32, mods, Some(result.pp), rosu::GameMode::from(replay.mode.raw()), reason).await
+    score_embed(map, &user, Some(replay.online_score_id), replay.score, result.accuracy, hits, replay.max_combo as u32, mods, Some(result.pp), Some(result.combo_annotation()), rosu::GameMode::from(replay.mode.raw()), reason).await
 }
 
 pub async fn score_embed_from_score(score: &rosu::Score, map: &rosu::BeatmapExtended, reason: Option<String>) -> Result<serenity::CreateEmbed, Error> {
     let user = score.get_user(osu::get_osu_instance()).await.expect("User has not been found");
     let hits = osu::formatter::osu_hits(&score.statistics, &score.mode);
     let mods = osu::formatter::mods_string(&score.mods);
-    score_embed(map, &user, Some(score.id), score.score, score.accuracy, hits, score.max_combo, mods, score.pp, score.mode, reason).await
+    let combo_state = osu::pp_calculator::calculate_score_by_score(score).await.ok().map(|result| result.combo_annotation());
+    score_embed(map, &user, Some(score.id), score.score, score.accuracy, hits, score.max_combo, mods, score.pp, combo_state, score.mode, reason).await
+}
+
+/// Short summary of a replay posted in chat. `result` is missing if the pp
+/// could not be calculated.
+pub fn replay_summary_embed(replay: &osu_db::Replay, map: &rosu::BeatmapExtended, result: Option<&CalculateScoreResponse>) -> serenity::CreateEmbed {
+    let player = replay.player_name.clone().unwrap_or("Unknown player".to_string());
+    let mode = rosu::GameMode::from(replay.mode.raw());
+    let mods = osu::formatter::convert_osu_db_to_mod_array(replay.mods).join("");
+    let max_combo = map.max_combo.map(|combo| format!("/{}x", combo)).unwrap_or_default();
+
+    let mut summary = vec![];
+    if let Some(result) = result {
+        summary.push(format!("**{:.2}%**", result.accuracy));
+    }
+    summary.push(format!("{}x{}", replay.max_combo, max_combo));
+    summary.push(format!("{} ❌", replay.count_miss));
+    if !mods.is_empty() {
+        summary.push(format!("+{}", mods));
+    }
+    if let Some(result) = result {
+        summary.push(format!("**{:.2}pp**", result.pp));
+        summary.push(result.combo_annotation());
+    }
+
+    let mut embed = serenity::CreateEmbed::default()
+        .author(serenity::CreateEmbedAuthor::new(format!("Replay by {} - {}", player, osu::formatter::game_mode_name(mode))))
+        .title(osu::formatter::map_title(map))
+        .url(map.url.to_string())
+        .description(summary.join(" · "))
+        .color(get_embed_color(&MessageState::INFO));
+    if let Some(mapset) = map.mapset.as_ref() {
+        embed = embed.thumbnail(mapset.covers.list.clone());
+    }
+    embed
+}
+
+/// Difficulty overview for a linked beatmap.
+pub fn beatmap_embed(map: &rosu::BeatmapExtended, mode: rosu::GameMode, result: &CalculateMapResponse) -> serenity::CreateEmbed {
+    let length = format!("{}:{:02}", map.seconds_drain / 60, map.seconds_drain % 60);
+    let attributes = format!(
+        "AR {:.1} · OD {:.1} · CS {:.1} · HP {:.1} · {:.0} BPM · {}",
+        result.ar, result.od, result.cs, result.hp, result.bpm, length,
+    );
+
+    let mut table = format!("{:<10}", "");
+    for accuracy in osu::pp_calculator::MAP_ACCURACIES {
+        table += &format!("{:>7}", format!("{}%", accuracy));
+    }
+    for row in &result.mods {
+        table += &format!("\n{:<10}", format!("{} {:.2}★", row.mods, row.star_rating));
+        for pp in row.pp {
+            table += &format!("{:>7}", format!("{:.0}pp", pp));
+        }
+    }
+
+    let mut embed = serenity::CreateEmbed::default()
+        .author(serenity::CreateEmbedAuthor::new(format!("{} - mapped by {}", osu::formatter::game_mode_name(mode), map.mapset.as_ref().map(|mapset| mapset.creator_name.to_string()).unwrap_or_default())))
+        .title(osu::formatter::map_title(map))
+        .url(map.url.to_string())
+        .description(format!("{}\n```\n{}\n```", attributes, table))
+        .color(get_embed_color(&MessageState::INFO));
+    if let Some(mapset) = map.mapset.as_ref() {
+        embed = embed.thumbnail(mapset.covers.list.clone());
+    }
+    embed
 }
 
 async fn score_embed(
@@ -57,6 +129,7 @@ async fn score_embed(
     max_combo: u32,
     mods: String,
     pp: Option<f32>,
+    combo_state: Option<String>,
     mode: rosu::GameMode,
     reason: Option<String>,
 ) -> Result<serenity::CreateEmbed, Error> {
@@ -80,7 +153,10 @@ async fn score_embed(
          .field("Score:", score.to_string(), true)
          .field("Accuracy:", format!("{:.2}",accuracy), true)
          .field("Hits:", hits, true)
-         .field("Combo:", max_combo.to_string() + "x", true)
+         .field("Combo:", match combo_state {
+             Some(combo_state) => format!("{}x · {}", max_combo, combo_state),
+             None => format!("{}x", max_combo),
+         }, true)
          .field("Mods:", mods, true)
          .field("PP:", format!("{:.2}", pp.unwrap_or(0.0)), true)
          .field("Reason:", reason.unwrap_or("No reason provided".into()), false))
@@ -123,25 +199,101 @@ pub fn render_and_upload_embed(
          .field("Upload Video:", upload_video_string, false))
 }
 
+pub fn render_queue_embed(title: &str, position: u64) -> serenity::CreateEmbed {
+    let author = serenity::CreateEmbedAuthor::new("Upload progress:");
+
+    serenity::CreateEmbed::default().author(author).color(get_embed_color(&MessageState::SUCCESS))
+         .title(title)
+         .field("Render queue:", format!("Queued at position {}", position), false)
+}
+
+pub fn render_cancelled_embed(title: &str, user: &serenity::User) -> serenity::CreateEmbed {
+    let author = serenity::CreateEmbedAuthor::new("Upload progress:");
+
+    serenity::CreateEmbed::default().author(author).color(get_embed_color(&MessageState::WARN))
+         .title(title)
+         .description(format!("Render has been cancelled by {}", user.mention()))
+}
+
 pub fn upload_result_embed (
     title: &String,
-    youtube_id: &String,
-    title_too_long: bool,
+    outcome: &UploadOutcome,
 ) -> Result<serenity::CreateEmbed, Error> {
     let mut embed = serenity::CreateEmbed::default();
     let author = serenity::CreateEmbedAuthor::new("Render and upload");
 
     embed = embed.author(author)
             .color(get_embed_color(&MessageState::SUCCESS))
-            .title(title)
-            .description(format!("Video has been uploaded successfully: https://studio.youtube.com/video/{}/edit", youtube_id));
+            .title(title);
 
-    if title_too_long {
-        embed = embed.field("Warning: The title was too long. Please adjust accordingly and set it yourself.", title, false);
+    match outcome {
+        UploadOutcome::Youtube { video_id, publish_at } => {
+            embed = embed.description(format!("Video has been uploaded successfully: https://studio.youtube.com/video/{}/edit", video_id));
+            if let Some(publish_at) = publish_at {
+                embed = embed.field("Scheduled", format!("Goes public <t:{}:F>", publish_at), false);
+            }
+        }
+        UploadOutcome::Archive { path } => {
+            embed = embed.description(format!("Video has been archived to `{}`", path.display()));
+        }
+        UploadOutcome::Discord { .. } => {
+            embed = embed.description("Video has been attached to this message");
+        }
     }
     Ok(embed)
 }
 
+pub fn feed_subscriptions_embed(subscriptions: &[feed_subscription::Model]) -> serenity::CreateEmbed {
+    let embed = serenity::CreateEmbed::default()
+        .author(serenity::CreateEmbedAuthor::new("Feeds"))
+        .color(get_embed_color(&MessageState::INFO));
+    if subscriptions.is_empty() {
+        return embed.description("No feeds are announced");
+    }
+
+    let lines: Vec<String> = subscriptions.iter().map(|subscription| {
+        let role = subscription.role_id.map(|role_id| format!(" · pings <@&{}>", role_id)).unwrap_or_default();
+        let template = subscription.template.as_ref().map(|template| format!("\n> {}", template)).unwrap_or_default();
+        format!(
+            "**#{}** [{}](https://www.youtube.com/channel/{}) → <#{}>{}{}",
+            subscription.id, subscription.youtube_channel_id, subscription.youtube_channel_id, subscription.discord_channel_id, role, template,
+        )
+    }).collect();
+    embed.description(lines.join("\n"))
+}
+
+pub fn feed_video_embed(video_id: &str, title: &str, thumbnail: &str) -> serenity::CreateEmbed {
+    serenity::CreateEmbed::default()
+        .author(serenity::CreateEmbedAuthor::new("New upload"))
+        .title(title)
+        .url(format!("https://youtu.be/{}", video_id))
+        .image(thumbnail)
+        .color(get_embed_color(&MessageState::SUCCESS))
+}
+
+pub fn uploaded_score_embed(video_id: &str, title: &str, thumbnail: &str, upload: &upload::Model, map: &rosu::BeatmapExtended, player: &str) -> serenity::CreateEmbed {
+    let mods = upload.mods.clone().filter(|mods| !mods.is_empty()).unwrap_or("NM".to_string());
+    let mut embed = feed_video_embed(video_id, title, thumbnail)
+        .field("Player", format!("[{}](https://osu.ppy.sh/users/{})", player, upload.osu_user_id), true)
+        .field("Map", format!("[{}](https://osu.ppy.sh/b/{})", osu::formatter::map_title(map), map.map_id), true)
+        .field("Mods", mods, true);
+    if let Some(pp) = upload.pp {
+        embed = embed.field("pp", format!("{:.2}", pp), true);
+    }
+    if let Some(skin) = &upload.skin {
+        embed = embed.field("Skin", skin, true);
+    }
+    embed
+}
+
+pub fn video_updated_embed(title: &str, video_id: &str) -> serenity::CreateEmbed {
+    serenity::CreateEmbed::default()
+        .author(serenity::CreateEmbedAuthor::new("Video"))
+        .color(get_embed_color(&MessageState::SUCCESS))
+        .title(title)
+        .description(format!("Video has been updated: https://studio.youtube.com/video/{}/edit", video_id))
+}
+
 pub fn suggestion_approved_embed (
     title: &String,
 ) -> Result<serenity::CreateEmbed, Error> {
@@ -158,6 +310,7 @@ pub fn suggestion_approved_embed (
 
 pub fn suggestion_declined_embed (
     title: &String,
+    reason: Option<&str>,
 ) -> Result<serenity::CreateEmbed, Error> {
     let mut embed = serenity::CreateEmbed::default();
     let author = serenity::CreateEmbedAuthor::new("Suggestion");
@@ -166,6 +319,109 @@ pub fn suggestion_declined_embed (
             .color(get_embed_color(&MessageState::SUCCESS))
             .title("❌ Your suggestion has been declined!")
             .description(format!("Score: {}", title));
+    if let Some(reason) = reason {
+        embed = embed.field("Reason:", reason, false);
+    }
 
     Ok(embed)
+}
+
+pub fn suggestion_queue_embed(suggestions: &[suggestion::Model], page: u64, pages: u64) -> serenity::CreateEmbed {
+    let author = serenity::CreateEmbedAuthor::new("Suggestion queue");
+    let embed = serenity::CreateEmbed::default().author(author).color(get_embed_color(&MessageState::INFO));
+    if suggestions.is_empty() {
+        return embed.description("No pending suggestions");
+    }
+
+    let lines: Vec<String> = suggestions.iter().map(|suggestion| {
+        let player = suggestion.player_name.clone().unwrap_or_else(|| format!("osu! user {}", suggestion.osu_user_id));
+        let map = suggestion.map_title.clone().unwrap_or_else(|| format!("beatmap {}", suggestion.map_id));
+        let map = match (suggestion.channel_id, suggestion.message_id) {
+            (Some(channel_id), Some(message_id)) => format!(
+                "[{}]({})",
+                map,
+                serenity::MessageId::new(message_id as u64).link(serenity::ChannelId::new(channel_id as u64), Some(*SERVER)),
+            ),
+            _ => map,
+        };
+        let pp = suggestion.pp.map(|pp| format!(" · {:.0}pp", pp)).unwrap_or_default();
+        let requested_by = if suggestion.auto_detected { "auto-detected".to_string() } else { format!("by <@{}>", suggestion.requested_by) };
+        format!(
+            "**#{}** {} · {}{} ({})\n{} <t:{}:R>",
+            suggestion.id, player, map, pp, osu::formatter::game_mode_name(rosu::GameMode::from(suggestion.mode as u8)), requested_by, suggestion.created_at,
+        )
+    }).collect();
+
+    embed.description(lines.join("\n\n"))
+        .footer(serenity::CreateEmbedFooter::new(format!("Page {}/{}", page + 1, pages.max(1))))
+}
+
+pub fn suggestion_limits_embed(limits: &suggestion_limit::Model) -> serenity::CreateEmbed {
+    let show = |value: Option<i64>, unit: &str| value.map(|value| format!("{}{}", value, unit)).unwrap_or("off".to_string());
+    serenity::CreateEmbed::default()
+        .author(serenity::CreateEmbedAuthor::new("Suggestion limits"))
+        .field("Pending per user", show(limits.max_pending, ""), true)
+        .field("Cooldown", show(limits.cooldown_seconds.map(|seconds| seconds / 60), " min"), true)
+        .field("Per 24 hours", show(limits.daily_cap, ""), true)
+        .color(get_embed_color(&MessageState::INFO))
+}
+
+pub fn rank_role_changes_embed(changes: &[RoleChange], dry_run: bool) -> serenity::CreateEmbed {
+    let author = serenity::CreateEmbedAuthor::new(if dry_run { "Rank roles (dry run)" } else { "Rank roles" });
+    let roles = |roles: &[serenity::RoleId], sign: char| roles.iter().map(|role| format!("{}<@&{}>", sign, role)).collect::<Vec<_>>();
+
+    let mut description = String::new();
+    for change in changes {
+        let mut line = vec![format!("<@{}> ({})", change.user, change.osu_username)];
+        line.extend(roles(&change.added, '+'));
+        line.extend(roles(&change.removed, '-'));
+        let line = line.join(" ");
+        if description.len() + line.len() > 4000 {
+            description.push('…');
+            break;
+        }
+        description = format!("{}{}\n", description, line);
+    }
+    if description.is_empty() {
+        description = "Every linked member already has the right roles".to_string();
+    }
+
+    serenity::CreateEmbed::default().author(author).description(description).color(get_embed_color(&MessageState::INFO))
+}
+
+pub fn youtube_device_code_embed(verification_url: &str, user_code: &str, expires_at: i64) -> serenity::CreateEmbed {
+    let author = serenity::CreateEmbedAuthor::new("YouTube authorization");
+
+    serenity::CreateEmbed::default().author(author).color(get_embed_color(&MessageState::INFO))
+         .title("The bot needs access to the YouTube channel")
+         .description(format!("Open {} and enter the code below while logged in as the channel owner.", verification_url))
+         .field("Code:", format!("`{}`", user_code), true)
+         .field("Expires:", format!("<t:{}:R>", expires_at), true)
+}
+
+pub fn youtube_status_embed(tokens: &[StoredToken]) -> serenity::CreateEmbed {
+    let mut embed = serenity::CreateEmbed::default()
+        .author(serenity::CreateEmbedAuthor::new("YouTube authorization"))
+        .color(get_embed_color(&MessageState::INFO));
+
+    if tokens.is_empty() {
+        return embed.description("No token has been stored yet. Run `/admin youtube authorize` to connect the channel.");
+    }
+
+    if !youtube::has_full_scope(tokens) {
+        embed = embed.description("The token only allows uploads. Editing videos and adding them to playlists needs the full `youtube` scope, so authorize once more (see the README).");
+    }
+    for stored in tokens {
+        let expiry = match stored.token.expires_at {
+            Some(expires_at) => format!("<t:{}:R>", expires_at.unix_timestamp()),
+            None => "never".to_string(),
+        };
+        let refresh = if stored.token.refresh_token.is_some() { "yes" } else { "no (re-authorize once it expires)" };
+        embed = embed.field(
+            stored.scopes.join("\n"),
+            format!("Access token expires: {}\nRefresh token: {}", expiry, refresh),
+            false,
+        );
+    }
+    embed
 }
\ No newline at end of file
@@ -0,0 +1,99 @@
+use poise::serenity_prelude::{self as serenity, CreateActionRow, CreateMessage};
+use rosu_v2::prelude as rosu;
+use time::OffsetDateTime;
+
+use crate::{Error, commands::suggest_commands, db, defaults::{COUNTRY_FIRSTS_CHANNEL, COUNTRY_FIRSTS_COUNTRY}, embeds, osu};
+
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+const RECENT_SCORES: usize = 50;
+/// The country leaderboard needs a supporter's user token, so the country #1
+/// is looked up among the global top scores instead. Country firsts below the
+/// global top 100 are never found.
+const LEADERBOARD_SIZE: u32 = 100;
+
+/// Scores set before this unix timestamp have been checked. Unset until the
+/// first poll, which only takes the baseline.
+const LAST_POLL: &str = "country_firsts_last_poll";
+
+pub async fn run_country_firsts(ctx: serenity::Context) -> Result<(), Error> {
+    let Some(channel) = *COUNTRY_FIRSTS_CHANNEL else {
+        return Ok(());
+    };
+    tracing::warn!(top = LEADERBOARD_SIZE, "Country firsts are only found within a map's global top scores, the country leaderboard needs a supporter's user token");
+    loop {
+        if let Err(e) = check_country_firsts(&ctx, channel).await {
+            tracing::error!(error = %e, "Checking for country firsts failed");
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+pub async fn check_country_firsts(ctx: &serenity::Context, channel: serenity::ChannelId) -> Result<(), Error> {
+    let now = OffsetDateTime::now_utc();
+    let Some(since) = db::get_task_state(LAST_POLL).await? else {
+        tracing::debug!("first loop... checking for country firsts starts with the next one");
+        db::set_task_state(LAST_POLL, now.unix_timestamp()).await?;
+        return Ok(());
+    };
+    let since = OffsetDateTime::from_unix_timestamp(since)?;
+
+    for user in db::linked_users().await? {
+        let scores = match osu::get_osu_instance().user_scores(user.osu_id as u32).recent().limit(RECENT_SCORES).await {
+            Ok(scores) => scores,
+            Err(e) => {
+                tracing::warn!(error = %e, osu_id = user.osu_id, "Could not fetch recent scores");
+                continue;
+            }
+        };
+        for score in scores {
+            if score.ended_at < since || !has_leaderboard(&score) {
+                continue;
+            }
+            match is_country_first(&score).await {
+                Ok(true) => (),
+                Ok(false) => continue,
+                Err(e) => {
+                    tracing::warn!(error = %e, score_id = score.id, "Could not fetch the leaderboard");
+                    continue;
+                }
+            }
+            tracing::info!(score_id = score.id, osu_id = user.osu_id, "New country #1");
+            if let Err(e) = announce(ctx, channel, &score, serenity::UserId::new(user.discord_id as u64)).await {
+                tracing::error!(error = %e, score_id = score.id, "Could not announce country #1");
+            }
+        }
+    }
+    db::set_task_state(LAST_POLL, now.unix_timestamp()).await?;
+    Ok(())
+}
+
+fn has_leaderboard(score: &rosu::Score) -> bool {
+    score.map.as_ref().is_some_and(|map| matches!(map.status, rosu::RankStatus::Ranked | rosu::RankStatus::Approved | rosu::RankStatus::Loved))
+}
+
+/// Whether `score` is the best score of `COUNTRY_FIRSTS_COUNTRY` on its map.
+async fn is_country_first(score: &rosu::Score) -> Result<bool, Error> {
+    let leaderboard = osu::get_osu_instance().beatmap_scores(score.map_id).mode(score.mode).global().limit(LEADERBOARD_SIZE).await?;
+    let country_first = leaderboard.scores.iter()
+        .find(|top| top.user.as_ref().is_some_and(|user| user.country_code.as_str() == COUNTRY_FIRSTS_COUNTRY.as_str()));
+    Ok(country_first.is_some_and(|top| top.id == score.id))
+}
+
+async fn announce(ctx: &serenity::Context, channel: serenity::ChannelId, score: &rosu::Score, discord_user: serenity::UserId) -> Result<(), Error> {
+    let map = osu::get_osu_instance().beatmap().map_id(score.map_id).await?;
+    let embed = embeds::score_embed_from_score(score, &map, None).await?;
+    let already_requested = db::has_score(score.id.to_string()).await?;
+    let button = suggest_commands::suggest_score_button(score.id, already_requested);
+
+    channel.send_message(ctx, CreateMessage::new()
+        .content(format!(
+            ":flag_{}: <@{}> took country #1 on **{}**!",
+            COUNTRY_FIRSTS_COUNTRY.to_lowercase(),
+            discord_user,
+            osu::formatter::map_title(&map)
+        ))
+        .embed(embed)
+        .components(vec![CreateActionRow::Buttons(vec![button])])
+    ).await?;
+    Ok(())
+}
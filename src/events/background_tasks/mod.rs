@@ -1,9 +1,20 @@
 use poise::serenity_prelude as serenity;
 
+use crate::events::background_tasks::country_firsts::run_country_firsts;
+use crate::events::background_tasks::rank_roles::run_rank_roles;
 use crate::events::background_tasks::refresh_feed::run_refresh_feed;
+use crate::events::background_tasks::track_players::run_track_players;
+use crate::generate::render_queue;
 
-mod refresh_feed;
+mod country_firsts;
+pub mod rank_roles;
+pub mod refresh_feed;
+mod track_players;
 
 pub fn start_background_tasks(ctx: &serenity::Context) {
     tokio::spawn(run_refresh_feed(ctx.clone()));
+    tokio::spawn(run_rank_roles(ctx.clone()));
+    tokio::spawn(run_track_players(ctx.clone()));
+    tokio::spawn(run_country_firsts(ctx.clone()));
+    render_queue::start_workers(ctx);
 }
\ No newline at end of file
@@ -0,0 +1,154 @@
+use poise::serenity_prelude as serenity;
+use rosu_v2::prelude as rosu;
+
+use crate::{Error, db, defaults::{RANK_ROLES, RANK_ROLES_MODE, SERVER}, osu};
+
+const SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RankKind {
+    Global,
+    Country,
+    Pp,
+}
+
+/// A role a linked member holds while their rank is at most (or pp at least) `threshold`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RankRole {
+    pub kind: RankKind,
+    pub threshold: u32,
+    pub role: serenity::RoleId,
+}
+
+impl RankRole {
+    fn is_met(&self, statistics: &rosu::UserStatistics) -> bool {
+        match self.kind {
+            RankKind::Global => statistics.global_rank.is_some_and(|rank| rank <= self.threshold),
+            RankKind::Country => statistics.country_rank.is_some_and(|rank| rank <= self.threshold),
+            RankKind::Pp => statistics.pp >= self.threshold as f32,
+        }
+    }
+}
+
+/// Parse rules like `global:100=<role id>;country:10=<role id>;pp:8000=<role id>`.
+pub fn parse_rank_roles(rules: &str) -> Result<Vec<RankRole>, String> {
+    rules.split(';').map(str::trim).filter(|rule| !rule.is_empty()).map(|rule| {
+        let invalid = || format!("invalid rank role `{}`", rule);
+        let (condition, role) = rule.split_once('=').ok_or_else(invalid)?;
+        let (kind, threshold) = condition.split_once(':').ok_or_else(invalid)?;
+        let kind = match kind.trim() {
+            "global" => RankKind::Global,
+            "country" => RankKind::Country,
+            "pp" => RankKind::Pp,
+            _ => return Err(invalid()),
+        };
+        Ok(RankRole {
+            kind,
+            threshold: threshold.trim().parse().map_err(|_| invalid())?,
+            role: serenity::RoleId::new(role.trim().parse().map_err(|_| invalid())?),
+        })
+    }).collect()
+}
+
+pub struct RoleChange {
+    pub user: serenity::UserId,
+    pub osu_username: String,
+    pub added: Vec<serenity::RoleId>,
+    pub removed: Vec<serenity::RoleId>,
+}
+
+pub async fn run_rank_roles(ctx: serenity::Context) -> Result<(), Error> {
+    if RANK_ROLES.is_empty() {
+        return Ok(());
+    }
+    loop {
+        if let Err(e) = sync_rank_roles(&ctx, false).await {
+            tracing::error!(error = %e, "Rank role sync failed");
+        }
+        tokio::time::sleep(SYNC_INTERVAL).await;
+    }
+}
+
+/// Work out which configured roles every linked member should hold and, unless
+/// `dry_run`, add and remove them. Members who left the server are skipped.
+pub async fn sync_rank_roles(ctx: &serenity::Context, dry_run: bool) -> Result<Vec<RoleChange>, Error> {
+    tracing::debug!(dry_run, "syncing rank roles");
+    let mut changes = vec![];
+    for user in db::linked_users().await? {
+        let discord_user = serenity::UserId::new(user.discord_id as u64);
+        let member = match SERVER.member(ctx, discord_user).await {
+            Ok(member) => member,
+            Err(_) => continue,
+        };
+        let player = match osu::get_osu_instance().user(user.osu_id as u32).mode(*RANK_ROLES_MODE).await {
+            Ok(player) => player,
+            Err(e) => {
+                tracing::warn!(error = %e, osu_id = user.osu_id, "Could not fetch statistics for rank roles");
+                continue;
+            }
+        };
+        let Some(statistics) = player.statistics.as_ref() else {
+            continue;
+        };
+
+        // A role can be listed by several rules; it stays as long as one of them is met.
+        let earned: Vec<serenity::RoleId> = RANK_ROLES.iter().filter(|rank_role| rank_role.is_met(statistics)).map(|rank_role| rank_role.role).collect();
+        let mut added = vec![];
+        let mut removed = vec![];
+        for rank_role in RANK_ROLES.iter() {
+            let role = rank_role.role;
+            let has_role = member.roles.contains(&role);
+            if earned.contains(&role) && !has_role && !added.contains(&role) {
+                added.push(role);
+            } else if !earned.contains(&role) && has_role && !removed.contains(&role) {
+                removed.push(role);
+            }
+        }
+        if added.is_empty() && removed.is_empty() {
+            continue;
+        }
+
+        tracing::info!(
+            user = %discord_user,
+            osu_id = user.osu_id,
+            added = ?added,
+            removed = ?removed,
+            dry_run,
+            "Rank roles changed"
+        );
+        if !dry_run {
+            for role in &added {
+                member.add_role(ctx, role).await?;
+            }
+            for role in &removed {
+                member.remove_role(ctx, role).await?;
+            }
+        }
+        changes.push(RoleChange {
+            user: discord_user,
+            osu_username: player.username.to_string(),
+            added,
+            removed,
+        });
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rank_roles() {
+        assert_eq!(
+            parse_rank_roles("global:100=1; country:10=2;pp:8000=3;").unwrap(),
+            vec![
+                RankRole { kind: RankKind::Global, threshold: 100, role: serenity::RoleId::new(1) },
+                RankRole { kind: RankKind::Country, threshold: 10, role: serenity::RoleId::new(2) },
+                RankRole { kind: RankKind::Pp, threshold: 8000, role: serenity::RoleId::new(3) },
+            ]
+        );
+        assert!(parse_rank_roles("score:100=1").is_err());
+        assert!(parse_rank_roles("global:100").is_err());
+    }
+}
@@ -1,89 +1,213 @@
 use poise::serenity_prelude::{self as serenity, CreateMessage};
 use quick_xml::{events::Event, Reader};
 use reqwest::StatusCode;
-use std::{env, sync::{Mutex, OnceLock}};
+use url::Url;
 
-use crate::{Error, defaults::NEW_VIDEOS_CHANNEL};
+use crate::{Error, db::{self, entities::feed_subscription}, defaults::{NEW_VIDEOS_CHANNEL, YOUTUBE_CHANNEL_ID}, embeds, osu};
 
-static LAST_VIDEO_ID: OnceLock<Mutex<Option<Vec<String>>>> = OnceLock::new();
-
-fn value() -> &'static Mutex<Option<Vec<String>>> {
-    LAST_VIDEO_ID.get_or_init(|| Mutex::new(None))
-}
-
-fn set(s: Vec<String>) {
-    *value().lock().unwrap() = Some(s);
-}
-
-fn get_clone() -> Option<Vec<String>> {
-    value().lock().unwrap().clone()
-}
+const DEFAULT_TEMPLATE: &str = "A new score has been uploaded!";
+/// Set once the feed from the environment has been turned into a subscription.
+const ENV_FEED_IMPORTED: &str = "env_feed_imported";
 
 pub async fn run_refresh_feed(ctx: serenity::Context) -> Result<(), Error> {
+    if let Err(e) = import_env_feed().await {
+        tracing::error!(error = %e, "Could not import the feed from the environment");
+    }
     loop {
-        refresh_feed(&ctx).await.ok();
+        match db::feed_subscriptions().await {
+            Ok(subscriptions) => {
+                for subscription in subscriptions {
+                    if let Err(e) = refresh_feed(&ctx, &subscription).await {
+                        tracing::warn!(error = %e, subscription = subscription.id, "Could not refresh feed");
+                    }
+                }
+            },
+            Err(e) => tracing::error!(error = %e, "Could not load feed subscriptions"),
+        }
         tokio::time::sleep(std::time::Duration::from_secs(180)).await;
     }
 }
 
-pub async fn refresh_feed(ctx: &serenity::Context) -> Result<(), Error> {
-    tracing::debug!("checking youtube for new video uploads");
-    let url = format!("https://www.youtube.com/feeds/videos.xml?channel_id={}", env::var("OSC_BOT_YOUTUBE_CHANNEL_ID").unwrap());
-    let last_video_ids = get_clone();
-    tracing::debug!(last = last_video_ids.clone().unwrap_or(vec!["empty".to_string()]).join(", "));
+/// `OSC_BOT_YOUTUBE_CHANNEL_ID` and `OSC_BOT_NEW_VIDEOS_CHANNEL` become the first
+/// subscription, so existing setups keep their feed. This happens once; a feed
+/// removed with `/admin feed remove` stays removed.
+async fn import_env_feed() -> Result<(), Error> {
+    let (Some(youtube_channel_id), Some(channel)) = (YOUTUBE_CHANNEL_ID.clone(), *NEW_VIDEOS_CHANNEL) else {
+        return Ok(());
+    };
+    if db::get_task_state(ENV_FEED_IMPORTED).await?.is_some() {
+        return Ok(());
+    }
+    if db::feed_subscriptions().await?.is_empty() {
+        let subscription = db::import_feed_subscription(youtube_channel_id, channel.get() as i64).await?;
+        tracing::info!(subscription = subscription.id, "Imported the feed from the environment");
+    }
+    db::set_task_state(ENV_FEED_IMPORTED, time::OffsetDateTime::now_utc().unix_timestamp()).await?;
+    Ok(())
+}
+
+/// The videos currently in a YouTube channel's feed, newest first.
+pub async fn fetch_feed(youtube_channel_id: &str) -> Result<Vec<FeedEntry>, Error> {
+    let mut url = Url::parse("https://www.youtube.com/feeds/videos.xml")?;
+    url.query_pairs_mut().append_pair("channel_id", youtube_channel_id);
 
     let c = reqwest::Client::new();
-    let req = c.get(&url);
+    let req = c.get(url);
 
-    let resp = req.send().await.unwrap();
+    let resp = req.send().await?;
     if resp.status() != StatusCode::OK { return Err(format!("http {}", resp.status()).into()); }
 
     let xml = resp.text().await?;
-    let video_ids = get_video_ids(&xml)?;
+    get_feed_entries(&xml)
+}
 
-    tracing::debug!(video_ids = video_ids.join(", "));
+pub async fn refresh_feed(ctx: &serenity::Context, subscription: &feed_subscription::Model) -> Result<(), Error> {
+    tracing::debug!(subscription = subscription.id, "checking youtube for new video uploads");
+    let entries = fetch_feed(&subscription.youtube_channel_id).await?;
+    let video_ids: Vec<String> = entries.iter().map(|entry| entry.video_id.clone()).collect();
 
+    tracing::debug!(video_ids = video_ids.join(", "));
 
-    let unwrapped_video_ids = match last_video_ids {
-        Some(video_ids) => video_ids,
-        None => {
-            set(video_ids);
-            tracing::debug!("first loop... checking for video uploads is skipped");
-            return Ok(())
+    // First fetch: remember the current feed instead of announcing all of it.
+    // An empty feed counts too, so the channel's first upload is announced.
+    let primed_key = db::feed_primed_key(subscription.id);
+    if db::get_task_state(&primed_key).await?.is_none() {
+        for video_id in video_ids {
+            db::insert_announced_video(subscription.id, video_id).await?;
         }
-    };
+        db::set_task_state(&primed_key, time::OffsetDateTime::now_utc().unix_timestamp()).await?;
+        tracing::debug!(subscription = subscription.id, "first run... stored the current feed without announcing it");
+        return Ok(())
+    }
 
-    for video_id in &video_ids {
-        if unwrapped_video_ids.contains(video_id) {
-            tracing::debug!("checking for new uploads has finished!");
-            return Ok(())
-        }
-        set(video_ids.clone());
-        tracing::info!(link = format!("https://youtu.be/{}", video_id), "New upload has been found!");
-        NEW_VIDEOS_CHANNEL.send_message(ctx,
-            CreateMessage::default().content(format!("A new score has been uploaded!\nhttps://youtu.be/{}", video_id))
-        ).await?;
+    let channel = serenity::ChannelId::new(subscription.discord_channel_id as u64);
+    let announced = db::announced_video_ids(subscription.id, &video_ids).await?;
+    // The feed lists the newest video first; announce the oldest first.
+    for entry in entries.iter().rev().filter(|entry| !announced.contains(&entry.video_id)) {
+        tracing::info!(link = format!("https://youtu.be/{}", entry.video_id), subscription = subscription.id, "New upload has been found!");
+        channel.send_message(ctx, announcement(ctx, subscription, entry).await?).await?;
+        db::insert_announced_video(subscription.id, entry.video_id.clone()).await?;
     }
     tracing::debug!("checking for new uploads has finished!");
     Ok(())
 }
 
-fn get_video_ids(xml: &str) -> Result<Vec<String>, Error> {
+/// The subscription's template with `{title}` and `{link}` filled in, after its role ping.
+fn headline(subscription: &feed_subscription::Model, entry: &FeedEntry) -> String {
+    let text = subscription.template.as_deref().unwrap_or(DEFAULT_TEMPLATE)
+        .replace("{title}", &entry.title)
+        .replace("{link}", &format!("https://youtu.be/{}", entry.video_id));
+    match subscription.role_id {
+        Some(role_id) => format!("<@&{}> {}", role_id, text),
+        None => text,
+    }
+}
+
+/// Videos the bot uploaded are announced with the score and ping the requester
+/// and the player; anything else only with what the feed says.
+async fn announcement(ctx: &serenity::Context, subscription: &feed_subscription::Model, entry: &FeedEntry) -> Result<CreateMessage, Error> {
+    let thumbnail = entry.thumbnail.clone().unwrap_or_else(|| format!("https://i.ytimg.com/vi/{}/hqdefault.jpg", entry.video_id));
+    // Uploads from before the upload table existed don't know their map and player.
+    let Some(upload) = db::find_upload_by_video_id(&entry.video_id).await?.filter(|upload| upload.map_id != 0) else {
+        return Ok(CreateMessage::default()
+            .content(headline(subscription, entry))
+            .embed(embeds::feed_video_embed(&entry.video_id, &entry.title, &thumbnail)));
+    };
+
+    let map = osu::get_osu_instance().beatmap().map_id(upload.map_id as u32).await?;
+    let player = osu::get_osu_instance().user(upload.osu_user_id as u32).await?;
+
+    let mut content = vec![headline(subscription, entry)];
+    // Auto-detected suggestions are requested by the bot itself.
+    if let Some(requested_by) = upload.requested_by.filter(|requested_by| *requested_by != ctx.cache.current_user().id.get() as i64) {
+        content.push(format!("Requested by <@{}>", requested_by));
+    }
+    if let Some(linked) = db::get_linked_user_by_osu_id(upload.osu_user_id).await? {
+        content.push(format!("Played by <@{}>", linked.discord_id));
+    }
+
+    Ok(CreateMessage::default()
+        .content(content.join("\n"))
+        .embed(embeds::uploaded_score_embed(&entry.video_id, &entry.title, &thumbnail, &upload, &map, &player.username)))
+}
+
+/// One `<entry>` of the channel's feed.
+#[derive(Debug, Default, PartialEq)]
+pub struct FeedEntry {
+    pub video_id: String,
+    pub title: String,
+    pub thumbnail: Option<String>,
+}
+
+fn get_feed_entries(xml: &str) -> Result<Vec<FeedEntry>, Error> {
+    // Not trimmed: a title's text is split around entities like `&amp;`.
     let mut r = Reader::from_str(xml);
-    r.config_mut().trim_text(true);
     let mut b = Vec::new();
     let mut in_id = false;
-    let mut video_ids: Vec<String> = vec![];
+    let mut in_title = false;
+    let mut entries: Vec<FeedEntry> = vec![];
+    // The feed's own <title> comes before the first <entry> and is skipped.
     loop {
         match r.read_event_into(&mut b) {
+            Ok(Event::Start(e)) if e.name().as_ref() == b"entry" => entries.push(FeedEntry::default()),
             Ok(Event::Start(e)) if e.name().as_ref().ends_with(b"videoId") => in_id = true,
             Ok(Event::End(e))   if e.name().as_ref().ends_with(b"videoId") => in_id = false,
-            Ok(Event::Text(t))  if in_id => video_ids.push(t.decode()?.into_owned()),
+            Ok(Event::Start(e)) if e.name().as_ref() == b"title" => in_title = true,
+            Ok(Event::End(e))   if e.name().as_ref() == b"title" => in_title = false,
+            Ok(Event::Empty(e)) if e.name().as_ref() == b"media:thumbnail" => {
+                if let (Some(entry), Some(url)) = (entries.last_mut(), e.try_get_attribute("url")?) {
+                    entry.thumbnail = Some(String::from_utf8_lossy(&url.value).into_owned());
+                }
+            },
+            Ok(Event::Text(t)) if in_id => if let Some(entry) = entries.last_mut() {
+                entry.video_id = t.decode()?.trim().to_string();
+            },
+            Ok(Event::Text(t)) if in_title => if let Some(entry) = entries.last_mut() {
+                entry.title.push_str(&t.decode()?);
+            },
+            Ok(Event::GeneralRef(reference)) if in_title => if let Some(entry) = entries.last_mut() {
+                match reference.resolve_char_ref()? {
+                    Some(character) => entry.title.push(character),
+                    None => {
+                        let name = reference.decode()?;
+                        entry.title.push_str(quick_xml::escape::resolve_predefined_entity(&name).unwrap_or_default());
+                    }
+                }
+            },
             Ok(Event::Eof) => break,
             Err(e) => return Err(e.into()),
             _ => {}
         }
         b.clear();
     }
-    Ok(video_ids)
+    for entry in &mut entries {
+        entry.title = entry.title.trim().to_string();
+    }
+    Ok(entries.into_iter().filter(|entry| !entry.video_id.is_empty()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_feed_entries() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns:yt="http://www.youtube.com/xml/schemas/2015" xmlns:media="http://search.yahoo.com/mrss/" xmlns="http://www.w3.org/2005/Atom">
+ <title>osu! Swiss</title>
+ <entry>
+  <yt:videoId>abc123</yt:videoId>
+  <title>Player | Artist - Song [Hard] +HDDT 98.50% &amp; FC</title>
+  <media:group>
+   <media:title>Player | Artist - Song [Hard] +HDDT 98.50% &amp; FC</media:title>
+   <media:thumbnail url="https://i1.ytimg.com/vi/abc123/hqdefault.jpg" width="480" height="360"/>
+  </media:group>
+ </entry>
+</feed>"#;
+        assert_eq!(get_feed_entries(xml).unwrap(), vec![FeedEntry {
+            video_id: "abc123".to_string(),
+            title: "Player | Artist - Song [Hard] +HDDT 98.50% & FC".to_string(),
+            thumbnail: Some("https://i1.ytimg.com/vi/abc123/hqdefault.jpg".to_string()),
+        }]);
+    }
 }
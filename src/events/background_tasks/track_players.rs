@@ -0,0 +1,84 @@
+use poise::serenity_prelude as serenity;
+use rosu_v2::prelude as rosu;
+use time::OffsetDateTime;
+
+use crate::{Error, commands::suggest_commands::{self, Refusal}, db, defaults::{TRACK_COUNTRY, TRACK_COUNTRY_TOP, TRACK_MIN_PP, TRACK_PLAYERS, TRACK_TOP_POSITION}, osu};
+
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+const RANKING_PAGE_SIZE: u32 = 50;
+
+/// Plays set before this unix timestamp are not new. Unset until the first
+/// poll, which only takes the baseline so enabling tracking doesn't suggest
+/// every top play. It only moves on once a poll went through.
+const LAST_POLL: &str = "track_players_last_poll";
+
+pub async fn run_track_players(ctx: serenity::Context) -> Result<(), Error> {
+    if !*TRACK_PLAYERS {
+        return Ok(());
+    }
+    loop {
+        if let Err(e) = track_players(&ctx).await {
+            tracing::error!(error = %e, "Tracking players failed");
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+pub async fn track_players(ctx: &serenity::Context) -> Result<(), Error> {
+    let now = OffsetDateTime::now_utc();
+    let Some(since) = db::get_task_state(LAST_POLL).await? else {
+        tracing::debug!("first loop... tracking new top plays starts with the next one");
+        db::set_task_state(LAST_POLL, now.unix_timestamp()).await?;
+        return Ok(());
+    };
+    let since = OffsetDateTime::from_unix_timestamp(since)?;
+
+    let players = tracked_players().await?;
+    tracing::debug!(players = players.len(), "checking tracked players for new top plays");
+    for osu_id in players {
+        let scores = match osu::get_osu_instance().user_scores(osu_id).best().mode(rosu::GameMode::Osu).limit(*TRACK_TOP_POSITION).await {
+            Ok(scores) => scores,
+            Err(e) => {
+                tracing::warn!(error = %e, osu_id, "Could not fetch top plays");
+                continue;
+            }
+        };
+        for score in scores {
+            if score.ended_at < since || score.pp.unwrap_or(0.0) < *TRACK_MIN_PP {
+                continue;
+            }
+            if db::has_score(score.id.to_string()).await? {
+                continue;
+            }
+            match suggest_commands::suggest_auto_detected(ctx, score.id).await? {
+                Ok(()) => tracing::info!(score_id = score.id, osu_id, "Auto-detected top play has been suggested"),
+                Err(Refusal(message, _)) => tracing::info!(score_id = score.id, reason = message, "Auto-detected top play was not suggested"),
+            }
+        }
+    }
+    db::set_task_state(LAST_POLL, now.unix_timestamp()).await?;
+    Ok(())
+}
+
+/// Linked members plus the top of `TRACK_COUNTRY`'s leaderboard, without duplicates.
+async fn tracked_players() -> Result<Vec<u32>, Error> {
+    let mut players: Vec<u32> = db::linked_users().await?.iter().map(|user| user.osu_id as u32).collect();
+
+    if let Some(country) = TRACK_COUNTRY.as_deref() {
+        let pages = TRACK_COUNTRY_TOP.div_ceil(RANKING_PAGE_SIZE);
+        let mut ranking = vec![];
+        for page in 1..=pages {
+            let rankings = osu::get_osu_instance().performance_rankings(rosu::GameMode::Osu).country(country).page(page).await?;
+            let last_page = rankings.next_page.is_none();
+            ranking.extend(rankings.ranking.into_iter().map(|user| user.user_id));
+            if last_page {
+                break;
+            }
+        }
+        players.extend(ranking.into_iter().take(*TRACK_COUNTRY_TOP as usize));
+    }
+
+    players.sort_unstable();
+    players.dedup();
+    Ok(players)
+}
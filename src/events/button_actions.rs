@@ -1,20 +1,67 @@
-use poise::serenity_prelude::{ self as serenity, ComponentInteraction, CreateAttachment, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditAttachments, EditMessage};
+use poise::serenity_prelude::{ self as serenity, ComponentInteraction, ComponentInteractionDataKind, CreateAttachment, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateMessage, EditAttachments, EditMessage, Mentionable};
+use std::time::Duration;
+
 use rosu_v2::prelude::BeatmapExtended;
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
-use crate::db::entities::user;
 use crate::defaults::EMPTY_VALUE;
-use crate::discord_helper::{ContextForFunctions, MessageState, user_has_replay_role};
-use crate::osu::formatter::convert_osu_db_to_mod_array;
+use crate::discord_helper::{ContextForFunctions, MessageState, is_blacklisted, user_has_replay_role};
 use crate::osu::get_osu_instance;
-use crate::{Error, db, embeds, osu};
-use crate::generate::{danser, thumbnail, upload, youtube_text};
+use crate::commands::suggest_commands;
+use crate::db::{self, entities::suggestion};
+use crate::{Error, embeds, osu};
+use crate::generate::{danser, render_queue, thumbnail, youtube_text};
+use crate::generate::uploader::UploadDestination;
+use crate::apis::youtube::{self, Privacy, PublishOptions};
+
+#[derive(Debug, poise::Modal)]
+#[name = "Edit video"]
+struct VideoEditModal {
+    #[name = "Title"]
+    #[min_length = 1]
+    #[max_length = 100]
+    title: String,
+    #[name = "Description"]
+    #[paragraph]
+    #[max_length = 4000]
+    description: String,
+}
+
+/// `execute_modal_on_component_interaction` takes an `AsRef<Context>`, which a
+/// plain `&Context` is not.
+struct ModalContext<'a>(&'a serenity::Context);
+
+impl AsRef<serenity::Context> for ModalContext<'_> {
+    fn as_ref(&self) -> &serenity::Context {
+        self.0
+    }
+}
+
+#[derive(Debug, poise::Modal)]
+#[name = "Decline suggestion"]
+struct DeclineModal {
+    #[name = "Reason (sent to the requester)"]
+    #[placeholder = "Leave empty to decline without a reason"]
+    #[paragraph]
+    #[max_length = 1000]
+    reason: Option<String>,
+}
 
 enum ScoreType {
     ScoreId,
     ReplayFile,
 }
 
+impl ScoreType {
+    fn parse(kind: &str) -> ScoreType {
+        match kind {
+            "replayfile" => ScoreType::ReplayFile,
+            _ => ScoreType::ScoreId,
+        }
+    }
+}
+
 struct ScoreMapping {
+    /// `None` for suggestions posted before they were stored in the database.
+    suggestion: Option<suggestion::Model>,
     reference: String,
     map: BeatmapExtended,
     score_type: ScoreType,
@@ -23,24 +70,123 @@ struct ScoreMapping {
 }
 
 impl ScoreMapping {
-    async fn new(ctx: &serenity::Context, contents: [&str; 5]) -> ScoreMapping {
-        let user_id: u64 = contents[3].parse().expect("not a u64");
-        let user = ctx.http.get_user(user_id.into()).await.unwrap();
-        let map = get_osu_instance().beatmap().map_id(contents[2].parse().unwrap()).await.unwrap();
-        ScoreMapping {
-            reference: contents[1].to_string(),
-            map: map,
-            score_type: match contents[0] {
-                "scoreid" => ScoreType::ScoreId,
-                "replayfile" => ScoreType::ReplayFile,
-                _ => ScoreType::ScoreId
-            },
+    /// Suggestion buttons carry the row id of the suggestion, which the caller
+    /// already loaded through `stored_suggestion`. Buttons on messages posted
+    /// before that still hold `kind:reference:map:user:skin`.
+    async fn load(ctx: &serenity::Context, data: &[&str], suggestion: Option<suggestion::Model>) -> Result<ScoreMapping, Error> {
+        if let Some(suggestion) = suggestion {
+            return Self::from_suggestion(ctx, suggestion).await;
+        }
+        match *data {
+            [kind, reference, map_id, user_id, skin_identifier] => {
+                let user = ctx.http.get_user(user_id.parse::<u64>()?.into()).await?;
+                let map = get_osu_instance().beatmap().map_id(map_id.parse()?).await?;
+                Ok(ScoreMapping {
+                    suggestion: None,
+                    reference: reference.to_string(),
+                    map,
+                    score_type: ScoreType::parse(kind),
+                    requesting_user: user,
+                    skin_identifier: if skin_identifier == EMPTY_VALUE {None} else {Some(skin_identifier.into())},
+                })
+            }
+            _ => Err(format!("Unexpected suggestion data {:?}", data).into()),
+        }
+    }
+
+    async fn from_suggestion(ctx: &serenity::Context, suggestion: suggestion::Model) -> Result<ScoreMapping, Error> {
+        let user = ctx.http.get_user(serenity::UserId::new(suggestion.requested_by as u64)).await?;
+        let map = get_osu_instance().beatmap().map_id(suggestion.map_id as u32).await?;
+        Ok(ScoreMapping {
+            reference: suggestion.reference.clone(),
+            map,
+            score_type: ScoreType::parse(&suggestion.kind),
             requesting_user: user,
-            skin_identifier: if contents[4] == EMPTY_VALUE {None} else {Some(contents[4].into())},
+            skin_identifier: suggestion.skin_identifier.clone(),
+            suggestion: Some(suggestion),
+        })
+    }
+
+
+    /// Auto-detected suggestions are requested by the bot itself, which has nobody to tell.
+    async fn notify_requester(&self, ctx: &serenity::Context, embed: serenity::CreateEmbed) -> Result<(), Error> {
+        if !self.requesting_user.bot {
+            self.requesting_user.dm(ctx, CreateMessage::default().add_embed(embed)).await?;
         }
+        Ok(())
+    }
+
+    async fn title(&self) -> Result<String, Error> {
+        Ok(match self.score_type {
+            ScoreType::ScoreId => {
+                let score = osu::get_osu_instance().score(self.reference.parse()?).await?;
+                youtube_text::generate_title_with_score(&score, &self.map).await
+            }
+            ScoreType::ReplayFile => {
+                let replay = danser::get_replay(&self.reference, self.map.checksum.as_ref().unwrap()).await?;
+                youtube_text::generate_title_with_replay(&replay, &self.map).await
+            }
+        })
     }
 }
 
+/// Buttons on the bot's replies to links and replays in chat, usable by everyone.
+const PUBLIC_ACTIONS: &[&str] = &["suggestScore", "suggestReplay", "replayThumbnail"];
+
+async fn handle_public_click(ctx: &serenity::Context, component: &ComponentInteraction, identifier: &str, data: &[&str]) -> Result<(), Error> {
+    component.create_response(ctx, CreateInteractionResponse::Defer(CreateInteractionResponseMessage::default().ephemeral(true))).await?;
+    if is_blacklisted(component.user.id).await? {
+        let embed = embeds::single_text_response_embed("You are blacklisted", MessageState::INFO);
+        component.edit_response(ctx, serenity::EditInteractionResponse::default().embed(embed)).await?;
+        return Ok(());
+    }
+
+    let suggested = match identifier {
+        "suggestScore" => {
+            let score_id: u64 = data.first().ok_or("Missing score id")?.parse()?;
+            suggest_commands::suggest_score_id(ctx, &component.user, score_id).await?
+        },
+        "suggestReplay" => {
+            let attachment = replay_attachment(ctx, component, data).await?;
+            suggest_commands::suggest_score_file(ctx, &component.user, attachment).await?
+        },
+        _ => {
+            let attachment = replay_attachment(ctx, component, data).await?;
+            let replay = osu_db::Replay::from_bytes(&attachment.download().await?)?;
+            let map = osu::get_beatmap_from_checksum(&replay.beatmap_hash).await.ok_or("Cannot find map related to the replay")?;
+            let thumbnail = thumbnail::generate_thumbnail_from_replay_file(&replay, &map, "").await;
+            component.edit_response(ctx, serenity::EditInteractionResponse::default()
+                .new_attachment(CreateAttachment::bytes(thumbnail, "thumbnail.jpeg"))
+            ).await?;
+            return Ok(());
+        },
+    };
+
+    let embed = match suggested {
+        Ok(()) => {
+            let mut message = component.message.clone();
+            message.edit(ctx, EditMessage::default().components(vec![])).await?;
+            embeds::single_text_response_embed("Score has been requested!", MessageState::INFO)
+        },
+        Err(suggest_commands::Refusal(text, state)) => embeds::single_text_response_embed(&text, state),
+    };
+    component.edit_response(ctx, serenity::EditInteractionResponse::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// The replay file a summary was posted for, found on the message it replies to.
+async fn replay_attachment(ctx: &serenity::Context, component: &ComponentInteraction, data: &[&str]) -> Result<serenity::Attachment, Error> {
+    let attachment_id: u64 = data.first().ok_or("Missing attachment id")?.parse()?;
+    let original = match &component.message.referenced_message {
+        Some(original) => *original.clone(),
+        None => {
+            let message_id = component.message.message_reference.as_ref().and_then(|reference| reference.message_id).ok_or("Replay message is gone")?;
+            component.channel_id.message(ctx, message_id).await?
+        },
+    };
+    Ok(original.attachments.into_iter().find(|attachment| attachment.id.get() == attachment_id).ok_or("Replay file is gone")?)
+}
+
 pub async fn handle_click(ctx: &serenity::Context, component: &ComponentInteraction) -> Result<(), Error> {
     tracing::info!(identifier = component.data.custom_id, "Interaction has been initiated");
     let mut parts: std::str::Split<'_, char> = component.data.custom_id.split(':');
@@ -48,6 +194,10 @@ pub async fn handle_click(ctx: &serenity::Context, component: &ComponentInteract
     let identifier = parts.next().unwrap();
     let data: Vec<&str> = parts.collect();
 
+    if PUBLIC_ACTIONS.contains(&identifier) {
+        return handle_public_click(ctx, component, identifier, &data).await;
+    }
+
     if !user_has_replay_role(ctx, &component.user).await.unwrap() {
         tracing::warn!(user = component.user.display_name(), "User tried to use interaction without permission");
         _ = component.create_response(ctx, 
@@ -63,89 +213,256 @@ pub async fn handle_click(ctx: &serenity::Context, component: &ComponentInteract
     let mut message = component.message.clone();
 
     let _ = match identifier {
-        "approveWithUpload" => {
-            let score = ScoreMapping::new(ctx, data.try_into().expect("Data must have 4 values")).await;
-            let title = match score.score_type {
-                ScoreType::ScoreId => upload_score_by_score(ctx, component, &score).await.unwrap(),
-                ScoreType::ReplayFile => upload_score_by_replay(ctx, component, &score).await.unwrap(),
+        "approveWithUpload" | "approveWithArchive" | "approveWithDiscord" => {
+            let destination = match identifier {
+                "approveWithArchive" => UploadDestination::Archive,
+                "approveWithDiscord" => UploadDestination::Discord,
+                _ => UploadDestination::Youtube,
             };
-            score.requesting_user.dm(ctx, CreateMessage::default().add_embed(embeds::suggestion_approved_embed(&title)?)).await?;
+            let suggestion = stored_suggestion(&data).await?;
+            if !claim(ctx, component, suggestion.as_ref(), db::SUGGESTION_APPROVED).await? {
+                return Ok(());
+            }
+            let suggestion_id = suggestion.as_ref().map(|suggestion| suggestion.id);
+            let queued = async {
+                let score = ScoreMapping::load(ctx, &data, suggestion).await?;
+                let title = queue_upload(ctx, component, &score, destination).await?;
+                Ok((score, title))
+            }.await;
+            let (score, title) = release_on_error(suggestion_id, queued).await?;
+            score.notify_requester(ctx, embeds::suggestion_approved_embed(&title)?).await?;
             message.edit(ctx, EditMessage::default().components(vec![])).await?;
             
         },
         "approveNoUpload" => {
-            component.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::default().content("Loading content..."))).await?;
-            let score = ScoreMapping::new(ctx, data.try_into().expect("Data must have 4 values")).await;
-            let title = match score.score_type {
-                ScoreType::ScoreId => get_score_metadata_by_score(ctx, component, &score).await.unwrap(),
-                ScoreType::ReplayFile => get_score_metadata_by_replay(ctx, component, &score).await.unwrap(),
-            };
-            score.requesting_user.dm(ctx, CreateMessage::default().add_embed(embeds::suggestion_approved_embed(&title)?)).await?;
+            let suggestion = stored_suggestion(&data).await?;
+            if !claim(ctx, component, suggestion.as_ref(), db::SUGGESTION_APPROVED).await? {
+                return Ok(());
+            }
+            let suggestion_id = suggestion.as_ref().map(|suggestion| suggestion.id);
+            let loaded = async {
+                component.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::default().content("Loading content..."))).await?;
+                let score = ScoreMapping::load(ctx, &data, suggestion).await?;
+                let title = match score.score_type {
+                    ScoreType::ScoreId => get_score_metadata_by_score(ctx, component, &score).await?,
+                    ScoreType::ReplayFile => get_score_metadata_by_replay(ctx, component, &score).await?,
+                };
+                Ok((score, title))
+            }.await;
+            let (score, title) = release_on_error(suggestion_id, loaded).await?;
+            score.notify_requester(ctx, embeds::suggestion_approved_embed(&title)?).await?;
             message.edit(ctx, EditMessage::default().components(vec![])).await?;
             
         },
         "decline" => {
-            let score = ScoreMapping::new(ctx, data.try_into().expect("Data must have 3 values")).await;
-            let title = match score.score_type {
-                ScoreType::ScoreId => {
-                    let score = osu::get_osu_instance().score(score.reference.parse().unwrap()).await?;
-                    let map = osu::get_osu_instance().beatmap().map_id(score.map_id).await?;
-                    youtube_text::generate_title_with_score(&score, &map).await
-                }
-                ScoreType::ReplayFile => {
-                    let replay = danser::get_replay(&score.reference, &score.map.checksum.as_ref().unwrap()).await?;
-                    youtube_text::generate_title_with_replay(&replay, &score.map).await
-                }
+            let suggestion = stored_suggestion(&data).await?;
+            if !still_pending(ctx, component, suggestion.as_ref()).await? {
+                return Ok(());
+            }
+            let Some(form) = poise::execute_modal_on_component_interaction::<DeclineModal>(ModalContext(ctx), component.clone(), None, Some(Duration::from_secs(15 * 60))).await? else {
+                return Ok(());
             };
-            score.requesting_user.dm(ctx, CreateMessage::default().add_embed(embeds::suggestion_declined_embed(&title)?)).await?;
-            message.edit(ctx, EditMessage::default().components(vec![])).await?;
+            decline(ctx, component, &data, suggestion, form.reason.filter(|reason| !reason.trim().is_empty())).await?;
+        }
+        "declineReason" => {
+            let suggestion = stored_suggestion(&data).await?;
+            if !still_pending(ctx, component, suggestion.as_ref()).await? {
+                return Ok(());
+            }
+            let reason = match &component.data.kind {
+                ComponentInteractionDataKind::StringSelect { values } => values.first().cloned(),
+                _ => None,
+            };
+            component.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
+            decline(ctx, component, &data, suggestion, reason).await?;
+        }
+        "suggestionQueue" => {
+            let (filter, page) = suggest_commands::parse_queue_custom_id(&data)?;
+            let (embed, components) = suggest_commands::queue_page(&filter, page).await?;
+            component.create_response(ctx, CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::default().embed(embed).components(components)
+            )).await?;
+        }
+        "cancelRender" => {
+            let job_id: i64 = data.first().ok_or("Render job id is missing")?.parse()?;
+            let response = match render_queue::cancel(ctx, job_id).await? {
+                Some(job) => CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::default()
+                        .embed(embeds::render_cancelled_embed(&job.title, &component.user))
+                        .components(vec![])
+                ),
+                None => CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::default()
+                        .embed(embeds::single_text_response_embed("This render has already finished and can no longer be cancelled", MessageState::INFO))
+                        .ephemeral(true)
+                ),
+            };
+            component.create_response(ctx, response).await?;
+        }
+        "editVideo" => {
+            let video_id = data.first().ok_or("Video id is missing")?;
+            edit_video(ctx, component, video_id).await?;
+        }
+        "videoPrivacy" => {
+            let video_id = data.first().ok_or("Video id is missing")?;
+            let privacy = data.get(1).and_then(|privacy| Privacy::parse(privacy)).ok_or("Privacy is missing")?;
+            component.defer_ephemeral(ctx).await?;
+            let embed = match youtube::update_video(video_id, None, None, Some(privacy)).await {
+                Ok(()) => embeds::single_text_response_embed(&format!("The video is {} now", privacy.as_str()), MessageState::SUCCESS),
+                Err(e) => embeds::single_text_response_embed(&format!("Could not update the video: {e}"), MessageState::ERROR),
+            };
+            component.edit_response(ctx, serenity::EditInteractionResponse::default().embed(embed)).await?;
         }
         _ => return Err("Identifier of component has not been found".into())
     };
     Ok(())
 }
 
-async fn upload_score_by_replay(ctx: &serenity::Context, component: &serenity::ComponentInteraction, score: &ScoreMapping) -> Result<String, Error> {
-    let mut cff = ContextForFunctions {
-        command_context: None,
-        reply: None,
-        event_context: Some(ctx),
-        component: Some(component)
+/// The stored suggestion a button belongs to; `None` for legacy buttons that
+/// carry the whole suggestion in their custom_id.
+async fn stored_suggestion(data: &[&str]) -> Result<Option<suggestion::Model>, Error> {
+    match *data {
+        [id] => Ok(Some(db::get_suggestion(id.parse()?).await?.ok_or("Suggestion does not exist")?)),
+        _ => Ok(None),
+    }
+}
+
+/// Cheap early out before opening the decline modal or menu; `decline` still
+/// claims the suggestion once the reason is known.
+async fn still_pending(ctx: &serenity::Context, component: &ComponentInteraction, suggestion: Option<&suggestion::Model>) -> Result<bool, Error> {
+    let Some(suggestion) = suggestion.filter(|suggestion| suggestion.status != db::SUGGESTION_PENDING) else {
+        return Ok(true);
     };
-    cff.send(embeds::render_and_upload_embed(&"...".to_string(), false, None, false)?).await?;
+    already_handled(ctx, component, &suggestion.status).await?;
+    Ok(false)
+}
 
-    let beatmap_hash = score.map.checksum.as_ref().unwrap();
-    let replay = danser::get_replay(&score.reference, &beatmap_hash).await.unwrap();
+/// Two staff members can click on the same suggestion before the buttons are
+/// gone; the status only leaves pending once, so only the first one gets through.
+async fn claim(ctx: &serenity::Context, component: &ComponentInteraction, suggestion: Option<&suggestion::Model>, status: &str) -> Result<bool, Error> {
+    let Some(suggestion) = suggestion else {
+        return Ok(true);
+    };
+    if db::claim_suggestion(suggestion.id, status, component.user.id.get() as i64, None).await? {
+        return Ok(true);
+    }
+    already_handled(ctx, component, &current_status(suggestion.id).await?).await?;
+    Ok(false)
+}
+
+/// An approval that fails before anything was queued puts the suggestion back
+/// to pending, so it can be approved again.
+async fn release_on_error<T>(suggestion_id: Option<i64>, result: Result<T, Error>) -> Result<T, Error> {
+    if result.is_err()
+        && let Some(id) = suggestion_id
+        && let Err(e) = db::release_suggestion(id).await {
+        tracing::error!(suggestion = id, error = %e, "Could not put the suggestion back to pending");
+    }
+    result
+}
 
-    let player = osu::get_osu_instance().user(replay.player_name.as_ref().unwrap()).await.unwrap();
+async fn current_status(id: i64) -> Result<String, Error> {
+    Ok(db::get_suggestion(id).await?.map(|suggestion| suggestion.status).unwrap_or_else(|| "handled".to_string()))
+}
 
-    let title = youtube_text::generate_title_with_replay(&replay, &score.map).await;
-    let user = user::Entity::find().filter(user::Column::OsuId.eq(player.user_id)).one(&db::get_db()).await?;
-    let mods = convert_osu_db_to_mod_array(replay.mods);
-    let skin = danser::resolve_correct_skin(user, score.skin_identifier.clone(), mods).await?;
-    upload::render_and_upload_by_replay(&cff, replay, score.map.clone(),  player,None, skin, false).await?;
-    Ok(title)
+fn already_handled_embed(status: &str) -> CreateEmbed {
+    embeds::single_text_response_embed(&format!("This suggestion has already been {}", status), MessageState::INFO)
+}
+
+async fn already_handled(ctx: &serenity::Context, component: &ComponentInteraction, status: &str) -> Result<(), Error> {
+    component.create_response(ctx, CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::default()
+            .embed(already_handled_embed(status))
+            .ephemeral(true)
+    )).await?;
+    Ok(())
 }
 
-async fn upload_score_by_score(ctx: &serenity::Context, component: &serenity::ComponentInteraction, score_mapping: &ScoreMapping) -> Result<String, Error> {
+/// Runs after the interaction has been answered (modal or select menu), so
+/// everything from here on goes through the message itself. The modal can stay
+/// open for a while, so the suggestion is only declined if it is still pending.
+async fn decline(ctx: &serenity::Context, component: &ComponentInteraction, data: &[&str], suggestion: Option<suggestion::Model>, reason: Option<String>) -> Result<(), Error> {
+    if let Some(suggestion) = &suggestion
+        && !db::claim_suggestion(suggestion.id, db::SUGGESTION_DECLINED, component.user.id.get() as i64, reason.clone()).await? {
+        let embed = already_handled_embed(&current_status(suggestion.id).await?);
+        component.create_followup(ctx, CreateInteractionResponseFollowup::default().embed(embed).ephemeral(true)).await?;
+        return Ok(());
+    }
+    let score = ScoreMapping::load(ctx, data, suggestion).await?;
+    let title = score.title().await?;
+    score.notify_requester(ctx, embeds::suggestion_declined_embed(&title, reason.as_deref())?).await?;
+
+    let mut message = component.message.clone();
+    let mut edit = EditMessage::default().components(vec![]);
+    if let Some(embed) = message.embeds.first() {
+        let declined = format!("by {}{}", component.user.mention(), reason.map(|reason| format!(": {}", reason)).unwrap_or_default());
+        edit = edit.embed(CreateEmbed::from(embed.clone()).field("❌ Declined", declined, false));
+    }
+    message.edit(ctx, edit).await?;
+    Ok(())
+}
+
+/// Opens a modal prefilled with what the bot knows about the video. The modal
+/// has to go out within Discord's three second window, so it is filled from the
+/// database instead of asking YouTube first.
+async fn edit_video(ctx: &serenity::Context, component: &ComponentInteraction, video_id: &str) -> Result<(), Error> {
+    let Some(video) = db::find_youtube_video(video_id).await? else {
+        component.create_response(ctx, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::default()
+                .embed(embeds::single_text_response_embed("This video was not uploaded by the bot", MessageState::WARN))
+                .ephemeral(true)
+        )).await?;
+        return Ok(());
+    };
+
+    let defaults = VideoEditModal { title: video.title, description: video.description };
+    let Some(edit) = poise::execute_modal_on_component_interaction(ModalContext(ctx), component.clone(), Some(defaults), Some(Duration::from_secs(15 * 60))).await? else {
+        return Ok(());
+    };
+
+    let embed = match youtube::update_video(video_id, Some(edit.title.clone()), Some(edit.description.clone()), None).await {
+        Ok(()) => {
+            db::update_youtube_video(video_id, Some(edit.title.clone()), Some(edit.description)).await?;
+            if let Some(result) = component.message.embeds.first() {
+                let mut message = component.message.clone();
+                message.edit(ctx, EditMessage::default().embed(CreateEmbed::from(result.clone()).title(&edit.title))).await?;
+            }
+            embeds::video_updated_embed(&edit.title, video_id)
+        }
+        Err(e) => embeds::single_text_response_embed(&format!("Could not update the video: {e}"), MessageState::ERROR),
+    };
+    component.create_followup(ctx, CreateInteractionResponseFollowup::default().embed(embed).ephemeral(true)).await?;
+    Ok(())
+}
+
+async fn queue_upload(ctx: &serenity::Context, component: &serenity::ComponentInteraction, score: &ScoreMapping, destination: UploadDestination) -> Result<String, Error> {
     let mut cff = ContextForFunctions {
         command_context: None,
         reply: None,
         event_context: Some(ctx),
-        component: Some(component)
+        component: Some(component),
+        message: None,
     };
-
     cff.send(embeds::render_and_upload_embed(&"...".to_string(), false, None, false)?).await?;
-    let score_id: u64 = score_mapping.reference.parse().unwrap();
-    let score = osu::get_osu_instance().score(score_id).await.expect("Score must exist");
-    let replay_bytes = osu::get_osu_instance().replay_raw(score_id).await.unwrap();
-    let map = osu::get_osu_instance().beatmap().map_id(score.map_id).await.expect("Beatmap must exist");
-    let title = youtube_text::generate_title_with_score(&score, &map).await;
-    danser::attach_replay(&map.checksum.as_ref().unwrap(), &score_id.to_string(), &replay_bytes).await?;
-    let user = user::Entity::find().filter(user::Column::OsuId.eq(score.user_id)).one(&db::get_db()).await?;
-    let acronym_mods: Vec<String> = score.mods.iter().map(|game_mod| game_mod.acronym().to_string()).collect();
-    let skin = danser::resolve_correct_skin(user, score_mapping.skin_identifier.clone(), acronym_mods).await?;
-    upload::render_and_upload_by_score(&cff, score, map, None, skin, false).await?;
+
+    let title = score.title().await?;
+    render_queue::enqueue(&cff, render_queue::RenderRequest {
+        kind: match score.score_type {
+            ScoreType::ScoreId => render_queue::JobKind::ScoreId,
+            ScoreType::ReplayFile => render_queue::JobKind::ReplayFile,
+        },
+        reference: score.reference.clone(),
+        map_id: score.map.map_id,
+        title: title.clone(),
+        subtitle: None,
+        skin_identifier: score.skin_identifier.clone(),
+        skip_beatmap_download: false,
+        destination,
+        publish: PublishOptions::default(),
+        requested_by: score.requesting_user.id,
+        approved_by: component.user.id,
+        suggestion_id: score.suggestion.as_ref().map(|suggestion| suggestion.id),
+    }).await?;
     Ok(title)
 }
 
@@ -159,8 +476,8 @@ async fn get_score_metadata_by_replay(ctx: &serenity::Context, component: &seren
 
     let timestamp = replay.timestamp.format("%d.%m.%Y at %H:%M").to_string();
     let title = youtube_text::generate_title_with_replay(&replay, &map).await;
-    let pp = osu::pp_calculator::calculate_score_by_replay(&replay, &map).await.ok().map(|r| r.pp);
-    let description = youtube_text::generate_description(user.user_id, map.map_id, None, Some(timestamp), pp, None);
+    let rating = osu::pp_calculator::calculate_score_by_replay(&replay, &map).await.ok();
+    let description = youtube_text::generate_description(user.user_id, map.map_id, None, Some(timestamp), rating.as_ref(), None);
     let thumbnail = thumbnail::generate_thumbnail_from_replay_file(&replay, &map, &"".to_string()).await;
     
     component.edit_response(ctx,serenity::EditInteractionResponse::default()
@@ -181,8 +498,8 @@ async fn get_score_metadata_by_score(ctx: &serenity::Context, component: &sereni
     
 
     let title = youtube_text::generate_title_with_score(&score, &map).await;
-    let pp = osu::pp_calculator::calculate_score_by_score(&score).await.ok().map(|r| r.pp);
-    let description = youtube_text::generate_description(score.user_id, map.map_id, Some(&score), None, pp, None);
+    let rating = osu::pp_calculator::calculate_score_by_score(&score).await.ok();
+    let description = youtube_text::generate_description(score.user_id, map.map_id, Some(&score), None, rating.as_ref(), None);
     let thumbnail = thumbnail::generate_thumbnail_from_score(&score, &map, &"".to_string()).await;
     
     component.edit_response(ctx,serenity::EditInteractionResponse::default()
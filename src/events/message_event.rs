@@ -1,8 +1,15 @@
-use poise::serenity_prelude::{self as serenity};
+use poise::serenity_prelude::{self as serenity, CreateActionRow, CreateButton, CreateMessage, ReactionType};
 
-use crate::{Error};
+use crate::{Error, commands::suggest_commands, db, defaults, embeds, osu};
 use crate::emojis;
 
+/// Replies to at most this many score links per message.
+const MAX_SCORE_LINKS: usize = 3;
+/// Unfurls at most this many beatmap links per message.
+const MAX_BEATMAP_LINKS: usize = 3;
+/// Summarizes at most this many replay files per message.
+const MAX_REPLAYS: usize = 3;
+
 pub async fn handle_message(ctx: &serenity::Context, new_message: &serenity::Message) -> Result<(), Error> {
     if new_message.author.bot {
         return Ok(());
@@ -11,6 +18,96 @@ pub async fn handle_message(ctx: &serenity::Context, new_message: &serenity::Mes
     if new_message.content.to_lowercase().contains("sata andagi") {
         new_message.react(ctx, emojis::SATA_ANDAGI).await?;
     }
+
+    for link in osu::links::score_links(&new_message.content).into_iter().take(MAX_SCORE_LINKS) {
+        if let Err(e) = reply_with_score(ctx, new_message, link).await {
+            tracing::warn!(score_id = link.score_id, error = %e, "Could not reply to score link");
+        }
+    }
+
+    for link in osu::links::beatmap_links(&new_message.content).into_iter().take(MAX_BEATMAP_LINKS) {
+        if let Err(e) = reply_with_beatmap(ctx, new_message, link).await {
+            tracing::warn!(map_id = link.map_id, error = %e, "Could not reply to beatmap link");
+        }
+    }
+
+    if defaults::REPLAY_CHANNELS.contains(&new_message.channel_id) {
+        let replays = new_message.attachments.iter()
+            .filter(|attachment| attachment.filename.to_lowercase().ends_with(".osr"))
+            .take(MAX_REPLAYS);
+        for attachment in replays {
+            if let Err(e) = reply_with_replay(ctx, new_message, attachment).await {
+                tracing::warn!(filename = attachment.filename, error = %e, "Could not reply to replay file");
+            }
+        }
+    }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Show the linked score with a button to suggest it.
+async fn reply_with_score(ctx: &serenity::Context, new_message: &serenity::Message, link: osu::links::ScoreLink) -> Result<(), Error> {
+    let mut request = osu::get_osu_instance().score(link.score_id);
+    if let Some(mode) = link.mode {
+        request = request.mode(mode);
+    }
+    let score = request.await?;
+    let map = osu::get_osu_instance().beatmap().map_id(score.map_id).await?;
+    let embed = embeds::score_embed_from_score(&score, &map, None).await?;
+
+    // Legacy links resolve to the score's current id, which is what /suggest uses.
+    let already_requested = db::has_score(score.id.to_string()).await?;
+    let button = suggest_commands::suggest_score_button(score.id, already_requested);
+
+    new_message.channel_id.send_message(ctx, CreateMessage::new()
+        .embed(embed)
+        .components(vec![CreateActionRow::Buttons(vec![button])])
+        .reference_message(new_message)
+    ).await?;
+    Ok(())
+}
+
+/// Show difficulty attributes and pp of the linked difficulty.
+async fn reply_with_beatmap(ctx: &serenity::Context, new_message: &serenity::Message, link: osu::links::BeatmapLink) -> Result<(), Error> {
+    let map = osu::get_osu_instance().beatmap().map_id(link.map_id).await?;
+    let mode = link.mode.unwrap_or(map.mode);
+    let result = osu::pp_calculator::calculate_map(&map, mode).await?;
+
+    new_message.channel_id.send_message(ctx, CreateMessage::new()
+        .embed(embeds::beatmap_embed(&map, mode, &result))
+        .reference_message(new_message)
+    ).await?;
+    Ok(())
+}
+
+/// Summarize a posted replay with buttons to suggest it or render its thumbnail.
+/// The buttons only carry the attachment id; the file is read back from the
+/// message the reply points to.
+async fn reply_with_replay(ctx: &serenity::Context, new_message: &serenity::Message, attachment: &serenity::Attachment) -> Result<(), Error> {
+    let bytes = attachment.download().await?;
+    let replay = osu_db::Replay::from_bytes(&bytes)?;
+    let map = osu::get_beatmap_from_checksum(&replay.beatmap_hash).await.ok_or("Cannot find map related to the replay")?;
+    let result = osu::pp_calculator::calculate_score_by_replay(&replay, &map).await.ok();
+    let embed = embeds::replay_summary_embed(&replay, &map, result.as_ref());
+
+    let already_requested = match &replay.replay_hash {
+        Some(hash) => db::has_score(hash.clone()).await?,
+        None => false,
+    };
+    let suggest_button = CreateButton::new(format!("suggestReplay:{}", attachment.id))
+        .label(if already_requested { "Already suggested" } else { "Suggest" })
+        .emoji(ReactionType::Unicode("📨".to_string()))
+        .style(serenity::ButtonStyle::Primary)
+        .disabled(already_requested);
+    let thumbnail_button = CreateButton::new(format!("replayThumbnail:{}", attachment.id))
+        .label("Thumbnail")
+        .emoji(ReactionType::Unicode("🖼️".to_string()))
+        .style(serenity::ButtonStyle::Secondary);
+
+    new_message.channel_id.send_message(ctx, CreateMessage::new()
+        .embed(embed)
+        .components(vec![CreateActionRow::Buttons(vec![suggest_button, thumbnail_button])])
+        .reference_message(new_message)
+    ).await?;
+    Ok(())
+}
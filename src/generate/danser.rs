@@ -133,7 +133,10 @@ pub async fn render(cff: &ContextForFunctions<'_>, title: &String, beatmap_hash:
         out.args(["-skin", replay_reference]);
     }
 
+    // Cancelling a render aborts the task driving this future; make sure the
+    // danser process goes down with it instead of rendering on in the background.
     let mut danser_terminal = out
+        .kill_on_drop(true)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()?;
@@ -356,7 +359,12 @@ pub async fn attach_replay(beatmap_hash: &String, replay_reference: &String, byt
 pub async fn get_replay(replay_reference: &String, beatmap_hash: &String) -> Result<osu_db::Replay, Error> {
     let replay_path = &format!("{}/Replays/{}/{}.osr", env::var("OSC_BOT_DANSER_PATH").expect("OSC_BOT_DANSER_PATH"), beatmap_hash, replay_reference);
     tracing::debug!(path = replay_path, "Getting parsed replay...");
-    let replay = osu_db::Replay::from_file(replay_path).unwrap();
+    // A missing or corrupt replay fails the job instead of panicking the worker.
+    let replay = osu_db::Replay::from_file(replay_path)
+        .map_err(|e| {
+            tracing::warn!(path = replay_path, error = %e, "Could not read replay");
+            format!("Could not read the stored replay: {}", e)
+        })?;
     tracing::debug!(path = replay_path, "Replay found and returned");
     Ok(replay)
 }
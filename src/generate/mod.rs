@@ -3,3 +3,5 @@ pub mod thumbnail;
 pub mod youtube_text;
 pub mod danser;
 pub mod upload;
+pub mod render_queue;
+pub mod uploader;
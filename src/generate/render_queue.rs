@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use poise::serenity_prelude::{self as serenity, CreateActionRow, CreateButton, ReactionType};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, sea_query::Expr};
+use tokio::sync::{Mutex, Notify};
+use tokio::task::{AbortHandle, JoinHandle};
+
+use crate::apis::youtube::{self, PublishOptions};
+use crate::db::entities::render_job;
+use crate::defaults::RENDER_WORKERS;
+use crate::discord_helper::{ContextForFunctions, MessageState};
+use crate::generate::{danser::{self, DanserFailure}, upload::{self, Provenance, RenderOptions}, uploader::UploadDestination};
+use crate::osu::formatter::convert_osu_db_to_mod_array;
+use crate::{Error, db, embeds, osu};
+
+pub const STATUS_QUEUED: &str = "queued";
+pub const STATUS_RENDERING: &str = "rendering";
+/// Rendered and on its way to the destination; past the point of cancelling.
+pub const STATUS_UPLOADING: &str = "uploading";
+pub const STATUS_FINISHED: &str = "finished";
+pub const STATUS_FAILED: &str = "failed";
+pub const STATUS_CANCELLED: &str = "cancelled";
+
+static QUEUE_NOTIFY: Notify = Notify::const_new();
+
+// Workers claim jobs one at a time so two of them never pick up the same row,
+// and a cancel never races a worker that is just starting the job.
+static CLAIM_LOCK: Mutex<()> = Mutex::const_new(());
+
+static RUNNING_JOBS: LazyLock<std::sync::Mutex<HashMap<i64, AbortHandle>>> = LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobKind {
+    ScoreId,
+    ReplayFile,
+}
+
+impl JobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ScoreId => "scoreid",
+            Self::ReplayFile => "replayfile",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "scoreid" => Some(Self::ScoreId),
+            "replayfile" => Some(Self::ReplayFile),
+            _ => None,
+        }
+    }
+}
+
+pub struct RenderRequest {
+    pub kind: JobKind,
+    /// Score id for `ScoreId`, replay hash for `ReplayFile`.
+    pub reference: String,
+    pub map_id: u32,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub skin_identifier: Option<String>,
+    pub skip_beatmap_download: bool,
+    pub destination: UploadDestination,
+    pub publish: PublishOptions,
+    /// Whoever asked for the render: the suggester, or staff using the command.
+    pub requested_by: serenity::UserId,
+    pub approved_by: serenity::UserId,
+    pub suggestion_id: Option<i64>,
+}
+
+fn now() -> i64 {
+    time::OffsetDateTime::now_utc().unix_timestamp()
+}
+
+/// Persist a render job and point the message `cff` sent at its queue position.
+/// The job is picked up by one of the render workers, which keep editing that
+/// same message once rendering starts.
+pub async fn enqueue(cff: &ContextForFunctions<'_>, request: RenderRequest) -> Result<render_job::Model, Error> {
+    let (channel_id, message_id) = cff.message_location().await?;
+    let job = render_job::ActiveModel {
+        status: Set(STATUS_QUEUED.to_string()),
+        kind: Set(request.kind.as_str().to_string()),
+        reference: Set(request.reference),
+        map_id: Set(request.map_id as i64),
+        title: Set(request.title),
+        subtitle: Set(request.subtitle),
+        skin_identifier: Set(request.skin_identifier),
+        skip_beatmap_download: Set(request.skip_beatmap_download),
+        destination: Set(request.destination.as_str().to_string()),
+        privacy: Set(Some(request.publish.privacy.as_str().to_string())),
+        publish_at: Set(request.publish.publish_at),
+        playlists: Set(Some(request.publish.playlists.join(","))),
+        requested_by: Set(Some(request.requested_by.get() as i64)),
+        approved_by: Set(Some(request.approved_by.get() as i64)),
+        suggestion_id: Set(request.suggestion_id),
+        channel_id: Set(channel_id.get() as i64),
+        message_id: Set(message_id.get() as i64),
+        created_at: Set(now()),
+        ..Default::default()
+    }.insert(&db::get_db()).await?;
+
+    let position = queue_position(&job).await?;
+    tracing::info!(job = job.id, reference = job.reference, position = position, "Render job has been queued");
+    cff.edit_with_components(embeds::render_queue_embed(&job.title, position), vec![], vec![cancel_button(job.id)]).await?;
+    QUEUE_NOTIFY.notify_one();
+    Ok(job)
+}
+
+fn cancel_button(job_id: i64) -> CreateActionRow {
+    let button = CreateButton::new(format!("cancelRender:{}", job_id))
+        .label("Cancel")
+        .emoji(ReactionType::Unicode("🛑".to_string()))
+        .style(serenity::ButtonStyle::Danger);
+    CreateActionRow::Buttons(vec![button])
+}
+
+/// Cancel a queued or rendering job. A rendering job has its task aborted, which
+/// kills danser; the worker then cleans up after it. Returns `None` if the job is
+/// already done and there is nothing left to cancel.
+pub async fn cancel(ctx: &serenity::Context, job_id: i64) -> Result<Option<render_job::Model>, Error> {
+    let guard = CLAIM_LOCK.lock().await;
+    let job = match render_job::Entity::find_by_id(job_id).one(&db::get_db()).await? {
+        Some(job) => job,
+        None => return Ok(None),
+    };
+    if !finish_job(job.id, &[STATUS_QUEUED, STATUS_RENDERING], STATUS_CANCELLED).await? {
+        return Ok(None);
+    }
+    // Only a rendering job has a task; it is registered under the claim lock.
+    let handle = RUNNING_JOBS.lock().unwrap().remove(&job.id);
+    drop(guard);
+
+    if let Some(handle) = handle {
+        handle.abort();
+        tracing::info!(job = job.id, "Running render job has been cancelled");
+        return Ok(Some(job));
+    }
+    tracing::info!(job = job.id, "Queued render job has been cancelled");
+    cleanup_job(&job).await;
+    refresh_queue_positions(ctx).await.ok();
+    Ok(Some(job))
+}
+
+async fn queue_position(job: &render_job::Model) -> Result<u64, Error> {
+    Ok(render_job::Entity::find()
+        .filter(render_job::Column::Status.eq(STATUS_QUEUED))
+        .filter(render_job::Column::Id.lte(job.id))
+        .count(&db::get_db()).await?)
+}
+
+pub fn start_workers(ctx: &serenity::Context) {
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        // Jobs that were rendering when the bot went down never finished; put them
+        // back in line before any worker starts claiming.
+        if let Err(error) = requeue_interrupted_jobs().await {
+            tracing::error!(error = %error, "Could not requeue interrupted render jobs");
+        }
+        if let Err(error) = fail_interrupted_uploads(&ctx).await {
+            tracing::error!(error = %error, "Could not fail interrupted uploads");
+        }
+        for worker in 0..*RENDER_WORKERS {
+            tokio::spawn(run_worker(ctx.clone(), worker));
+        }
+        tracing::info!(workers = *RENDER_WORKERS, "Render workers have been started");
+    });
+}
+
+async fn requeue_interrupted_jobs() -> Result<(), Error> {
+    let result = render_job::Entity::update_many()
+        .col_expr(render_job::Column::Status, Expr::value(STATUS_QUEUED))
+        .col_expr(render_job::Column::StartedAt, Expr::value(Option::<i64>::None))
+        .filter(render_job::Column::Status.eq(STATUS_RENDERING))
+        .exec(&db::get_db()).await?;
+    if result.rows_affected > 0 {
+        tracing::warn!(jobs = result.rows_affected, "Interrupted render jobs have been requeued");
+    }
+    Ok(())
+}
+
+/// An upload that was cut off may still have gone through, so it is not
+/// retried; the message tells staff to check before rendering it again.
+async fn fail_interrupted_uploads(ctx: &serenity::Context) -> Result<(), Error> {
+    let interrupted = render_job::Entity::find()
+        .filter(render_job::Column::Status.eq(STATUS_UPLOADING))
+        .all(&db::get_db()).await?;
+    for job in interrupted {
+        tracing::warn!(job = job.id, reference = job.reference, "Render job was interrupted while uploading");
+        finish_job(job.id, &[STATUS_UPLOADING], STATUS_FAILED).await?;
+        report_failure(ctx, &job, "The bot restarted while this video was being uploaded. Check whether it arrived before rendering it again.").await;
+        cleanup_job(&job).await;
+    }
+    Ok(())
+}
+
+/// Called once danser is done. Takes the claim lock so a cancel either aborts
+/// the job before this or finds it uploading and leaves it alone. Fails if the
+/// job was cancelled in the meantime; the video is still recorded for cleanup.
+pub async fn mark_uploading(job_id: i64, video_path: &str) -> Result<(), Error> {
+    let _guard = CLAIM_LOCK.lock().await;
+    render_job::ActiveModel {
+        id: Set(job_id),
+        video_path: Set(Some(video_path.to_string())),
+        ..Default::default()
+    }.update(&db::get_db()).await?;
+    let result = render_job::Entity::update_many()
+        .col_expr(render_job::Column::Status, Expr::value(STATUS_UPLOADING))
+        .filter(render_job::Column::Id.eq(job_id))
+        .filter(render_job::Column::Status.eq(STATUS_RENDERING))
+        .exec(&db::get_db()).await?;
+    if result.rows_affected == 0 {
+        return Err("The render job has been cancelled".into());
+    }
+    Ok(())
+}
+
+async fn run_worker(ctx: serenity::Context, worker: usize) {
+    loop {
+        let (job, handle) = match start_next_job(&ctx).await {
+            Ok(Some(started)) => started,
+            Ok(None) => {
+                // Enqueueing wakes a worker right away; the timeout only guards
+                // against a missed notification.
+                tokio::time::timeout(Duration::from_secs(60), QUEUE_NOTIFY.notified()).await.ok();
+                continue;
+            }
+            Err(error) => {
+                tracing::error!(worker = worker, error = %error, "Could not claim render job");
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                continue;
+            }
+        };
+
+        tracing::info!(worker = worker, job = job.id, reference = job.reference, "Render job has been started");
+        refresh_queue_positions(&ctx).await.ok();
+
+        let result = handle.await;
+        RUNNING_JOBS.lock().unwrap().remove(&job.id);
+        let (status, failure) = match result {
+            Ok(Ok(())) => (STATUS_FINISHED, None),
+            Ok(Err(error)) => {
+                tracing::error!(job = job.id, error = %error, "Render job failed");
+                let message = error
+                    .downcast_ref::<DanserFailure>()
+                    .map(DanserFailure::user_message)
+                    .unwrap_or_else(|| format!("Render failed: {error}"));
+                (STATUS_FAILED, Some(message))
+            }
+            Err(error) if error.is_cancelled() => (STATUS_CANCELLED, None),
+            Err(error) => {
+                tracing::error!(job = job.id, error = %error, "Render job panicked");
+                (STATUS_FAILED, Some("Render failed unexpectedly. Ping the operator.".to_string()))
+            }
+        };
+        // A cancel already marked the job and rewrote the message; don't report
+        // the failure its abort caused on top of that.
+        match finish_job(job.id, &[STATUS_RENDERING, STATUS_UPLOADING], status).await {
+            Ok(true) => if let Some(message) = failure {
+                report_failure(&ctx, &job, &message).await;
+            },
+            Ok(false) => tracing::debug!(job = job.id, "Render job had already been cancelled"),
+            Err(error) => tracing::error!(job = job.id, error = %error, "Could not update render job status"),
+        }
+        if status != STATUS_FINISHED {
+            cleanup_job(&job).await;
+        }
+    }
+}
+
+/// Claim the oldest queued job and start it in its own task, so a panic deep in
+/// the render path only fails this job instead of killing the worker, and so the
+/// cancel button has something to abort.
+async fn start_next_job(ctx: &serenity::Context) -> Result<Option<(render_job::Model, JoinHandle<Result<(), Error>>)>, Error> {
+    let _guard = CLAIM_LOCK.lock().await;
+    let job = match render_job::Entity::find()
+        .filter(render_job::Column::Status.eq(STATUS_QUEUED))
+        .order_by_asc(render_job::Column::Id)
+        .one(&db::get_db()).await? {
+        Some(job) => job,
+        None => return Ok(None),
+    };
+
+    let mut job: render_job::ActiveModel = job.into();
+    job.status = Set(STATUS_RENDERING.to_string());
+    job.started_at = Set(Some(now()));
+    let job = job.update(&db::get_db()).await?;
+
+    let handle = tokio::spawn(process_job(ctx.clone(), job.clone()));
+    RUNNING_JOBS.lock().unwrap().insert(job.id, handle.abort_handle());
+    Ok(Some((job, handle)))
+}
+
+/// Move the job to a final `status` if it is still in one of `from`. Returns
+/// false if something else (usually a cancel) finished it first.
+async fn finish_job(job_id: i64, from: &[&str], status: &str) -> Result<bool, Error> {
+    let result = render_job::Entity::update_many()
+        .col_expr(render_job::Column::Status, Expr::value(status))
+        .col_expr(render_job::Column::FinishedAt, Expr::value(now()))
+        .filter(render_job::Column::Id.eq(job_id))
+        .filter(render_job::Column::Status.is_in(from.iter().copied()))
+        .exec(&db::get_db()).await?;
+    Ok(result.rows_affected == 1)
+}
+
+/// Every job that is still waiting moved up by one, so rewrite their messages.
+async fn refresh_queue_positions(ctx: &serenity::Context) -> Result<(), Error> {
+    let queued = render_job::Entity::find()
+        .filter(render_job::Column::Status.eq(STATUS_QUEUED))
+        .order_by_asc(render_job::Column::Id)
+        .all(&db::get_db()).await?;
+
+    for (index, job) in queued.iter().enumerate() {
+        job_context(ctx, job).edit(embeds::render_queue_embed(&job.title, index as u64 + 1), vec![]).await?;
+    }
+    Ok(())
+}
+
+fn job_context<'a>(ctx: &'a serenity::Context, job: &render_job::Model) -> ContextForFunctions<'a> {
+    ContextForFunctions {
+        command_context: None,
+        reply: None,
+        event_context: Some(ctx),
+        component: None,
+        message: Some((
+            serenity::ChannelId::new(job.channel_id as u64),
+            serenity::MessageId::new(job.message_id as u64),
+        )),
+    }
+}
+
+/// Remove whatever a cancelled or failed job left in the danser directories,
+/// including the rendered video once the job got that far.
+async fn cleanup_job(job: &render_job::Model) {
+    // `job` is the row as it was claimed; the video path is stored later on.
+    let video_path = match render_job::Entity::find_by_id(job.id).one(&db::get_db()).await {
+        Ok(stored) => stored.and_then(|stored| stored.video_path).unwrap_or_default(),
+        Err(error) => {
+            tracing::warn!(job = job.id, error = %error, "Could not read rendered video path for cleanup");
+            String::new()
+        }
+    };
+    if !video_path.is_empty() {
+        tokio::fs::remove_file(&video_path).await.ok();
+    }
+    let beatmap_hash = match osu::get_osu_instance().beatmap().map_id(job.map_id as u32).await {
+        Ok(map) => map.checksum.unwrap_or_default(),
+        Err(error) => {
+            tracing::warn!(job = job.id, error = %error, "Could not resolve beatmap for cleanup");
+            return;
+        }
+    };
+    danser::cleanup_files(&beatmap_hash, &job.reference, &video_path).await;
+}
+
+/// Finished jobs replace the cancel button themselves; failed ones lose it here.
+async fn report_failure(ctx: &serenity::Context, job: &render_job::Model, message: &str) {
+    job_context(ctx, job)
+        .edit_with_components(embeds::single_text_response_embed(message, MessageState::ERROR), vec![], vec![])
+        .await
+        .ok();
+}
+
+/// Jobs queued before publish options existed have none stored and get the
+/// configured defaults.
+fn publish_options(job: &render_job::Model) -> PublishOptions {
+    let defaults = PublishOptions::default();
+    PublishOptions {
+        privacy: job.privacy.as_deref().and_then(youtube::Privacy::parse).unwrap_or(defaults.privacy),
+        publish_at: job.publish_at,
+        playlists: job.playlists.as_deref().map(youtube::parse_playlists).unwrap_or(defaults.playlists),
+    }
+}
+
+async fn process_job(ctx: serenity::Context, job: render_job::Model) -> Result<(), Error> {
+    let cff = job_context(&ctx, &job);
+    let map = osu::get_osu_instance().beatmap().map_id(job.map_id as u32).await?;
+    let beatmap_hash = map.checksum.clone().ok_or("Beatmap has no checksum")?;
+    let destination = UploadDestination::parse(&job.destination)
+        .ok_or_else(|| format!("Unknown upload destination {}", job.destination))?;
+    let kind = JobKind::parse(&job.kind).ok_or_else(|| format!("Unknown render job kind {}", job.kind))?;
+    let mut options = RenderOptions {
+        job_id: job.id,
+        subtitle: job.subtitle.clone(),
+        skin: None,
+        skip_beatmap_download: job.skip_beatmap_download,
+        destination,
+        publish: publish_options(&job),
+        provenance: Provenance {
+            kind,
+            requested_by: job.requested_by,
+            approved_by: job.approved_by,
+            suggestion_id: job.suggestion_id,
+            requested_at: job.created_at,
+        },
+    };
+
+    match kind {
+        JobKind::ScoreId => {
+            let score_id: u64 = job.reference.parse()?;
+            let score = osu::get_osu_instance().score(score_id).await?;
+            // Re-fetched here rather than at enqueue time so jobs survive a
+            // container restart that wiped the danser directories.
+            let replay_bytes = osu::get_osu_instance().replay_raw(score_id).await?;
+            danser::attach_replay(&beatmap_hash, &job.reference, &replay_bytes).await?;
+            let user = db::get_linked_user_by_osu_id(score.user_id as i64).await?;
+            let acronym_mods: Vec<String> = score.mods.iter().map(|game_mod| game_mod.acronym().to_string()).collect();
+            options.skin = danser::resolve_correct_skin(user, job.skin_identifier.clone(), acronym_mods).await?;
+            upload::render_and_upload_by_score(&cff, score, map, options).await
+        }
+        JobKind::ReplayFile => {
+            let replay = danser::get_replay(&job.reference, &beatmap_hash).await?;
+            let player = osu::get_osu_instance().user(replay.player_name.as_ref().ok_or("Replay has no player name")?).await?;
+            let user = db::get_linked_user_by_osu_id(player.user_id as i64).await?;
+            let mods = convert_osu_db_to_mod_array(replay.mods);
+            options.skin = danser::resolve_correct_skin(user, job.skin_identifier.clone(), mods).await?;
+            upload::render_and_upload_by_replay(&cff, replay, map, player, options).await
+        }
+    }
+}
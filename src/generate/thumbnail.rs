@@ -73,17 +73,18 @@ pub async fn generate_thumbnail_from_replay_file(replay: &Replay, map: &rosu::Be
     let result = osu::pp_calculator::calculate_score_by_replay(replay, &map).await.expect("Local PP calculation to succeed");
     let mods = osu::formatter::convert_osu_db_to_mod_array(replay.mods);
     let grade = osu::formatter::calculate_grade_from_accuracy(result.accuracy, replay.count_miss > 0, mods.contains(&"HD".to_string()));
+    let mods = mods.into_iter().map(|game_mod| (game_mod, None)).collect();
     generate_thumbnail(user, map, subtitle, Some(result.pp), result.accuracy, replay.max_combo as u32, mods, &grade).await
 }
 
 pub async fn generate_thumbnail_from_score(score: &rosu::Score, map: &rosu::BeatmapExtended, subtitle: &str) -> Vec<u8> {
     tracing::info!(scoreid = score.id, "Generating thumbnail from score...");
     let user = score.get_user(osu::get_osu_instance()).await.expect("User should exist");
-    let mods: Vec<String> = score.mods.iter().map(|beatmap| beatmap.acronym().to_string()).collect();
+    let mods = score.mods.iter().map(|game_mod| (game_mod.acronym().to_string(), osu::formatter::custom_rate(game_mod))).collect();
     generate_thumbnail(user, map, subtitle, score.pp, score.accuracy, score.max_combo, mods, &score.grade).await
 }
 
-async fn generate_thumbnail(user: rosu::UserExtended, map: &rosu::BeatmapExtended, subtitle: &str, pp: Option<f32>, accuracy: f32, max_combo: u32, mods: Vec<String>, grade:&rosu::Grade) -> Vec<u8> {
+async fn generate_thumbnail(user: rosu::UserExtended, map: &rosu::BeatmapExtended, subtitle: &str, pp: Option<f32>, accuracy: f32, max_combo: u32, mods: Vec<(String, Option<f64>)>, grade:&rosu::Grade) -> Vec<u8> {
     let user_stats = user.statistics.as_ref().expect("Stats must exist");
     let mapset = map.mapset.as_ref().expect("Mapset must exist");
 
@@ -135,10 +136,16 @@ async fn generate_thumbnail(user: rosu::UserExtended, map: &rosu::BeatmapExtende
 
     let length = mods.len();
     let start = 960 - (length as u32 * (MOD_WIDTH + SPACE_BETWEEN_MODS) / 2);
-    for (i, gamemod) in mods.iter().enumerate() {
+    for (i, (gamemod, rate)) in mods.iter().enumerate() {
         let current_round: u32 = i as u32;
         let mod_image = image::load_from_memory(image_binaries::get_mod_bytes(gamemod)).unwrap();
-        image::imageops::overlay(&mut score_bg, &mod_image, (start + (current_round * (MOD_WIDTH + SPACE_BETWEEN_MODS))) as i64, 770);
+        let x = start + (current_round * (MOD_WIDTH + SPACE_BETWEEN_MODS));
+        image::imageops::overlay(&mut score_bg, &mod_image, x as i64, 770);
+        // Custom speeds go right below their mod
+        if let Some(rate) = rate {
+            let y = 770 + mod_image.height() + 30;
+            write_centered(&mut score_bg, &white, (x + MOD_WIDTH / 2) as i32, y as i32, PxScale::from(40.0), &font, &osu::formatter::rate_string(*rate));
+        }
     }
 
     let mut grade_image = image::load_from_memory(image_binaries::get_rank_bytes(grade)).unwrap();
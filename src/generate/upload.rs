@@ -1,23 +1,64 @@
-use poise::serenity_prelude::CreateAttachment;
+use poise::{ChoiceParameter, serenity_prelude::{self as serenity, CreateActionRow, CreateAttachment, CreateButton, ReactionType}};
 use rosu_v2::prelude as rosu;
+use sea_orm::{ActiveModelTrait, ActiveValue::Set};
 
-use crate::{Error, apis::{self, osc_web::OscWebSkin, youtube}, discord_helper::{ContextForFunctions, MessageState}, embeds, generate::{danser, danser::DanserFailure, thumbnail, youtube_text}, osu::pp_calculator};
+use crate::{Error, db::{self, entities::upload}, apis::{self, osc_web::OscWebSkin, youtube::{Privacy, PublishOptions}}, discord_helper::ContextForFunctions, embeds, generate::{danser, render_queue::{self, JobKind}, thumbnail, uploader::{RenderedVideo, UploadDestination, UploadOutcome}, youtube_text}, osu::{formatter, pp_calculator}};
+
+/// Who asked for a render and who let it through, carried from the render job
+/// into the `upload` row.
+pub struct Provenance {
+    pub kind: JobKind,
+    pub requested_by: Option<i64>,
+    pub approved_by: Option<i64>,
+    pub suggestion_id: Option<i64>,
+    pub requested_at: i64,
+}
+
+/// How a job is rendered and where it goes, as stored on the render job.
+pub struct RenderOptions {
+    pub job_id: i64,
+    pub subtitle: Option<String>,
+    pub skin: Option<OscWebSkin>,
+    pub skip_beatmap_download: bool,
+    pub destination: UploadDestination,
+    pub publish: PublishOptions,
+    pub provenance: Provenance,
+}
+
+/// Everything worked out from the score or replay before rendering starts.
+pub struct VideoDetails {
+    pub title: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub thumbnail: Vec<u8>,
+    pub mods: String,
+    pub pp: Option<f64>,
+}
 
 pub async fn render_and_upload_by_score(
     cff: &ContextForFunctions<'_>,
     score: rosu::Score,
     map: rosu::BeatmapExtended,
-    subtitle: Option<String>,
-    skin: Option<OscWebSkin>,
-    skip_beatmap_download: bool,
+    options: RenderOptions,
 ) -> Result<(), Error> {
     let title = youtube_text::generate_title_with_score(&score, &map).await;
     cff.edit(embeds::render_and_upload_embed(&title, false, None, false)?, vec![]).await?;
-    let thumbnail = thumbnail::generate_thumbnail_from_score(&score, &map, &subtitle.unwrap_or("".to_string())).await;
-    let pp = pp_calculator::calculate_score_by_score(&score).await.ok().map(|r| r.pp);
-    let description = youtube_text::generate_description(score.user_id, map.map_id, Some(&score), None, pp, skin.as_ref());
+    let thumbnail = thumbnail::generate_thumbnail_from_score(&score, &map, options.subtitle.as_deref().unwrap_or("")).await;
+    let rating = pp_calculator::calculate_score_by_score(&score).await.ok();
+    let description = youtube_text::generate_description(score.user_id, map.map_id, Some(&score), None, rating.as_ref(), options.skin.as_ref());
+    let username = score.user.as_ref().map(|user| user.username.to_string()).unwrap_or_default();
+    let mods: Vec<String> = score.mods.iter().map(|game_mod| game_mod.acronym().to_string()).collect();
+    let tags = youtube_text::generate_tags(&username, &mods, rating.as_ref().map(|r| r.star_rating).unwrap_or(0.0));
 
-    render_and_upload(cff, &score.id.to_string(), &map.mapset_id, &map.checksum.unwrap(), title, description, thumbnail, skin, skip_beatmap_download).await?;
+    let details = VideoDetails {
+        title,
+        description,
+        tags,
+        thumbnail,
+        mods: formatter::mods_string(&score.mods),
+        pp: rating.map(|r| f64::from(r.pp)),
+    };
+    render_and_upload(cff, &score.id.to_string(), &map, score.user_id, details, options).await?;
     Ok(())
 }
 
@@ -26,37 +67,46 @@ pub async fn render_and_upload_by_replay(
     replay: osu_db::Replay,
     map: rosu::BeatmapExtended,
     user: rosu::UserExtended,
-    subtitle: Option<String>,
-    skin: Option<OscWebSkin>,
-    skip_beatmap_download: bool
+    options: RenderOptions,
 ) -> Result<(), Error> {
     let title = youtube_text::generate_title_with_replay(&replay, &map).await;
     cff.edit(embeds::render_and_upload_embed(&title, false, None, false)?, vec![]).await?;
     let timestamp = replay.timestamp.format("%d.%m.%Y at %H:%M").to_string();
-    let thumbnail = thumbnail::generate_thumbnail_from_replay_file(&replay, &map, &subtitle.unwrap_or("".to_string())).await;
-    let pp = pp_calculator::calculate_score_by_replay(&replay, &map).await.ok().map(|r| r.pp);
-    let description = youtube_text::generate_description(user.user_id, map.map_id, None, Some(timestamp), pp, skin.as_ref());
-    render_and_upload(cff, &replay.replay_hash.unwrap(), &map.mapset_id, &map.checksum.unwrap(), title, description, thumbnail, skin, skip_beatmap_download).await?;
+    let thumbnail = thumbnail::generate_thumbnail_from_replay_file(&replay, &map, options.subtitle.as_deref().unwrap_or("")).await;
+    let rating = pp_calculator::calculate_score_by_replay(&replay, &map).await.ok();
+    let description = youtube_text::generate_description(user.user_id, map.map_id, None, Some(timestamp), rating.as_ref(), options.skin.as_ref());
+    let mods = formatter::convert_osu_db_to_mod_array(replay.mods);
+    let tags = youtube_text::generate_tags(&user.username, &mods, rating.as_ref().map(|r| r.star_rating).unwrap_or(0.0));
 
+    let details = VideoDetails {
+        title,
+        description,
+        tags,
+        thumbnail,
+        mods: mods.join(""),
+        pp: rating.map(|r| f64::from(r.pp)),
+    };
+    render_and_upload(cff, &replay.replay_hash.unwrap(), &map, user.user_id, details, options).await?;
     Ok(())
 }
 
 pub async fn render_and_upload(
     cff: &ContextForFunctions<'_>,
     replay_reference: &String,
-    mapset_id: &u32,
-    map_hash: &String,
-    title: String,
-    description: String,
-    thumbnail: Vec<u8>,
-    skin: Option<OscWebSkin>,
-    skip_beatmap_download: bool
+    map: &rosu::BeatmapExtended,
+    osu_user_id: u32,
+    details: VideoDetails,
+    options: RenderOptions,
 ) -> Result<(), Error> {
+    let VideoDetails { title, description, tags, thumbnail, mods, pp } = details;
+    let RenderOptions { job_id, skin, skip_beatmap_download, destination, publish, provenance, .. } = options;
+    let map_hash = map.checksum.as_ref().ok_or("Beatmap has no checksum")?;
     if !skip_beatmap_download {
-        apis::download_mapset(cff, mapset_id, replay_reference).await?;
+        apis::download_mapset(cff, &map.mapset_id, replay_reference).await?;
     }
     let replay_bytes = danser::get_replay_bytes(&replay_reference, &map_hash).await?;
     cff.edit(embeds::render_and_upload_embed(&title, true, None, false)?, vec![]).await?;
+    let skin_name = skin.as_ref().map(|s| s.dir_name.clone());
     match skin {
         // The community skin is danser's installed default, so a no-pick fallback
         // to it (no matched_modifier) renders without a per-render download — it
@@ -66,25 +116,85 @@ pub async fn render_and_upload(
         None => true,
     };
     
-    let replay_path = match danser::render(cff, &title, map_hash, replay_reference).await {
-        Ok(p) => p,
+    // A failed render goes back to the render queue, which marks the job as
+    // failed and shows the reason on the message.
+    let replay_path = danser::render(cff, &title, map_hash, replay_reference).await?;
+    render_queue::mark_uploading(job_id, &replay_path).await?;
+    let video = RenderedVideo {
+        reference: replay_reference.clone(),
+        path: replay_path.clone(),
+        title: title.clone(),
+        description,
+        tags,
+        thumbnail,
+        publish,
+    };
+    let outcome = match destination.uploader().upload(&video).await {
+        Ok(outcome) => outcome,
         Err(e) => {
-            let msg = e
-                .downcast_ref::<DanserFailure>()
-                .map(|f| f.user_message())
-                .unwrap_or_else(|| format!("Render failed: {e}"));
-            cff.edit(
-                embeds::single_text_response_embed(&msg, MessageState::ERROR),
-                vec![],
-            ).await?;
-            return Ok(());
+            danser::cleanup_files(map_hash, replay_reference, &replay_path).await;
+            return Err(format!("Upload to {} failed: {e}", destination.name()).into());
         }
     };
-    let title_too_long = title.len() > 100;
-    let video_title = if title_too_long {&"temporary title please replace".to_string()} else {&title};
-    let video_id = youtube::upload(&replay_path, video_title.clone(), description, thumbnail).await.unwrap();
     cff.edit(embeds::render_and_upload_embed(&title, true, Some("100%".to_string()), true)?, vec![]).await?;
-    danser::cleanup_files(&map_hash, &replay_reference, &replay_path).await;
-    cff.edit(embeds::upload_result_embed(&title, &video_id, title_too_long)?, vec![CreateAttachment::bytes(replay_bytes, "replay.osr")]).await?;
+    danser::cleanup_files(map_hash, replay_reference, &replay_path).await;
+
+    let video_id = match &outcome {
+        UploadOutcome::Youtube { video_id, .. } => Some(video_id.clone()),
+        _ => None,
+    };
+    let record = upload::ActiveModel {
+        kind: Set(provenance.kind.as_str().to_string()),
+        reference: Set(replay_reference.clone()),
+        map_id: Set(map.map_id as i64),
+        osu_user_id: Set(osu_user_id as i64),
+        requested_by: Set(provenance.requested_by),
+        approved_by: Set(provenance.approved_by),
+        skin: Set(skin_name),
+        destination: Set(destination.as_str().to_string()),
+        // What YouTube got, so the edit buttons can be prefilled with it.
+        title: Set(video_id.as_ref().map(|_| youtube_text::fit_to_limit(&video.title))),
+        description: Set(video_id.as_ref().map(|_| video.description.clone())),
+        video_id: Set(video_id),
+        requested_at: Set(provenance.requested_at),
+        uploaded_at: Set(time::OffsetDateTime::now_utc().unix_timestamp()),
+        mods: Set(Some(mods)),
+        pp: Set(pp),
+        ..Default::default()
+    };
+    if let Err(e) = record.insert(&db::get_db()).await {
+        tracing::error!(reference = replay_reference, error = %e, "Could not record upload");
+    }
+    if let Some(suggestion_id) = provenance.suggestion_id
+        && let Err(e) = db::set_suggestion_status(suggestion_id, db::SUGGESTION_UPLOADED, None, None).await {
+        tracing::error!(suggestion = suggestion_id, error = %e, "Could not mark suggestion as uploaded");
+    }
+
+    let mut attachments = vec![CreateAttachment::bytes(replay_bytes, "replay.osr")];
+    let mut components = vec![];
+    match &outcome {
+        UploadOutcome::Discord { attachment } => attachments.push(attachment.clone()),
+        UploadOutcome::Youtube { video_id, .. } => components.push(video_edit_buttons(video_id)),
+        UploadOutcome::Archive { .. } => {}
+    }
+    cff.edit_with_components(embeds::upload_result_embed(&title, &outcome)?, attachments, components).await?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Buttons below the upload result that change the video on YouTube.
+pub fn video_edit_buttons(video_id: &str) -> CreateActionRow {
+    let mut buttons = vec![
+        CreateButton::new(format!("editVideo:{}", video_id))
+            .label("Edit title & description")
+            .emoji(ReactionType::Unicode("✏️".to_string()))
+            .style(serenity::ButtonStyle::Primary),
+    ];
+    for privacy in [Privacy::Public, Privacy::Unlisted, Privacy::Private] {
+        buttons.push(
+            CreateButton::new(format!("videoPrivacy:{}:{}", video_id, privacy.as_str()))
+                .label(privacy.name())
+                .style(serenity::ButtonStyle::Secondary),
+        );
+    }
+    CreateActionRow::Buttons(buttons)
+}
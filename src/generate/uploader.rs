@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+
+use poise::serenity_prelude::CreateAttachment;
+use tokio::fs;
+
+use crate::Error;
+use crate::apis::youtube::{self, PublishOptions};
+use crate::generate::youtube_text;
+use crate::defaults::ARCHIVE_PATH;
+
+/// Discord rejects bot attachments above this size.
+const DISCORD_ATTACHMENT_LIMIT: u64 = 10 * 1024 * 1024;
+
+/// Where a finished render ends up.
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter)]
+pub enum UploadDestination {
+    #[name = "YouTube"]
+    Youtube,
+    #[name = "Archive"]
+    Archive,
+    #[name = "Discord"]
+    Discord,
+}
+
+impl UploadDestination {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Youtube => "youtube",
+            Self::Archive => "archive",
+            Self::Discord => "discord",
+        }
+    }
+
+    pub fn parse(destination: &str) -> Option<Self> {
+        match destination {
+            "youtube" => Some(Self::Youtube),
+            "archive" => Some(Self::Archive),
+            "discord" => Some(Self::Discord),
+            _ => None,
+        }
+    }
+
+    pub fn uploader(&self) -> Box<dyn Uploader> {
+        match self {
+            Self::Youtube => Box::new(YoutubeUploader),
+            Self::Archive => Box::new(ArchiveUploader),
+            Self::Discord => Box::new(DiscordUploader),
+        }
+    }
+}
+
+pub struct RenderedVideo {
+    /// Score id or replay hash the video was rendered from.
+    pub reference: String,
+    pub path: String,
+    pub title: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub thumbnail: Vec<u8>,
+    pub publish: PublishOptions,
+}
+
+pub enum UploadOutcome {
+    Youtube { video_id: String, publish_at: Option<i64> },
+    Archive { path: PathBuf },
+    /// The video goes out as an attachment on the progress message.
+    Discord { attachment: CreateAttachment },
+}
+
+pub trait Uploader: Send + Sync {
+    fn upload<'a>(&'a self, video: &'a RenderedVideo) -> poise::BoxFuture<'a, Result<UploadOutcome, Error>>;
+}
+
+pub struct YoutubeUploader;
+
+impl Uploader for YoutubeUploader {
+    fn upload<'a>(&'a self, video: &'a RenderedVideo) -> poise::BoxFuture<'a, Result<UploadOutcome, Error>> {
+        Box::pin(async move {
+            // Generated titles already fit; this covers titles typed in by hand.
+            let title = youtube_text::fit_to_limit(&video.title);
+            let video_id = youtube::upload(&video.path, title, video.description.clone(), video.tags.clone(), video.thumbnail.clone(), &video.publish).await?;
+            Ok(UploadOutcome::Youtube { video_id, publish_at: video.publish.publish_at })
+        })
+    }
+}
+
+/// Moves the video (plus thumbnail and text) into `OSC_BOT_ARCHIVE_PATH`.
+pub struct ArchiveUploader;
+
+impl Uploader for ArchiveUploader {
+    fn upload<'a>(&'a self, video: &'a RenderedVideo) -> poise::BoxFuture<'a, Result<UploadOutcome, Error>> {
+        Box::pin(async move {
+            let archive = ARCHIVE_PATH.as_ref().ok_or("OSC_BOT_ARCHIVE_PATH is not set")?;
+            fs::create_dir_all(archive).await?;
+
+            let target = archive.join(format!("{}.mp4", video.reference));
+            move_file(Path::new(&video.path), &target).await?;
+            if !video.thumbnail.is_empty() {
+                // Named after the thumbnail's actual format, not an assumed one.
+                let extension = image::guess_format(&video.thumbnail).ok()
+                    .and_then(|format| format.extensions_str().first().copied())
+                    .unwrap_or("jpg");
+                fs::write(archive.join(format!("{}.{}", video.reference, extension)), &video.thumbnail).await?;
+            }
+            fs::write(archive.join(format!("{}.txt", video.reference)), format!("{}\n\n{}", video.title, video.description)).await?;
+
+            tracing::info!(path = ?target, "Video has been archived");
+            Ok(UploadOutcome::Archive { path: target })
+        })
+    }
+}
+
+async fn move_file(from: &Path, to: &Path) -> Result<(), Error> {
+    // rename fails across filesystems (e.g. a mounted archive volume).
+    if fs::rename(from, to).await.is_err() {
+        fs::copy(from, to).await?;
+        fs::remove_file(from).await?;
+    }
+    Ok(())
+}
+
+/// Posts short renders straight into Discord.
+pub struct DiscordUploader;
+
+impl Uploader for DiscordUploader {
+    fn upload<'a>(&'a self, video: &'a RenderedVideo) -> poise::BoxFuture<'a, Result<UploadOutcome, Error>> {
+        Box::pin(async move {
+            let size = fs::metadata(&video.path).await?.len();
+            if size > DISCORD_ATTACHMENT_LIMIT {
+                return Err(format!(
+                    "The video is {:.1} MiB, Discord only accepts up to {} MiB",
+                    size as f64 / (1024.0 * 1024.0),
+                    DISCORD_ATTACHMENT_LIMIT / (1024 * 1024),
+                ).into());
+            }
+
+            let bytes = fs::read(&video.path).await?;
+            Ok(UploadOutcome::Discord { attachment: CreateAttachment::bytes(bytes, format!("{}.mp4", video.reference)) })
+        })
+    }
+}
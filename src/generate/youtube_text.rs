@@ -1,43 +1,164 @@
 use rosu_v2::prelude as rosu;
 
 use crate::apis::osc_web::OscWebSkin;
-use crate::osu::{self, formatter::mods_string};
+use crate::osu;
+use crate::osu::pp_calculator::CalculateScoreResponse;
+
+/// YouTube refuses titles longer than this many characters.
+pub const TITLE_LIMIT: usize = 100;
+
+/// Shortened difficulty and map names keep at least this many characters.
+const MIN_NAME_LENGTH: usize = 12;
 
 pub async fn generate_title_with_score(score: &rosu::Score, map: &rosu::BeatmapExtended) -> String {
     tracing::info!("Generating title by score...");
     let username: &String = &score.user.as_ref().expect("User must exist").username.to_string();
-    let mods = mods_string(&score.mods);
+    let mods = osu::formatter::mod_labels(&score.mods);
 
-    let stars = match osu::pp_calculator::calculate_score_by_score(score).await {
-        Ok(result) => result.star_rating,
-        Err(_) => 0.0,
+    let (stars, tag) = match osu::pp_calculator::calculate_score_by_score(score).await {
+        Ok(result) => (result.star_rating, result.title_tag()),
+        Err(_) => (0.0, None),
     };
 
-    generate_title(map, &username, stars, mods)
+    generate_title(map, username, stars, &mods, tag)
 }
 
 pub async fn generate_title_with_replay(replay: &osu_db::Replay, map: &rosu::BeatmapExtended) -> String {
     tracing::info!("Generating title by replay...");
     let mods = osu::formatter::convert_osu_db_to_mod_array(replay.mods);
 
-    let stars = match osu::pp_calculator::calculate_score_by_replay(replay, map).await {
-        Ok(result) => result.star_rating,
-        Err(_) => 0.0,
+    let (stars, tag) = match osu::pp_calculator::calculate_score_by_replay(replay, map).await {
+        Ok(result) => (result.star_rating, result.title_tag()),
+        Err(_) => (0.0, None),
     };
 
-    generate_title(map, replay.player_name.as_ref().unwrap_or(&"Unknown player".to_string()), stars, mods.join(""))
+    generate_title(map, replay.player_name.as_ref().unwrap_or(&"Unknown player".to_string()), stars, &mods, tag)
 }
 
-fn generate_title(map: &rosu::BeatmapExtended, username: &String, stars: f32, mods: String) -> String {
+fn generate_title(map: &rosu::BeatmapExtended, username: &str, stars: f32, mods: &[String], tag: Option<&str>) -> String {
     let mapset = map.mapset.as_ref().expect("missing mapset");
+    let title = fit_title(username, &mapset.artist, &mapset.title, &map.version, stars, mods, tag);
+    tracing::info!("Title has been generated successfully");
+    title
+}
+
+struct TitleParts {
+    username: String,
+    artist: Option<String>,
+    title: String,
+    version: String,
+    stars: f32,
+    star_decimals: usize,
+    mods: Vec<String>,
+    tag: Option<String>,
+}
 
-    if mods != "" {
-         return format!("{} | {} - {} [{}] {:.2}⭐ +{}", username, mapset.artist, mapset.title, map.version, stars, mods);
+impl TitleParts {
+    fn render(&self) -> String {
+        let map = match &self.artist {
+            Some(artist) => format!("{} - {}", artist, self.title),
+            None => self.title.clone(),
+        };
+        let mut title = format!("{} | {} [{}] {:.*}⭐", self.username, map, self.version, self.star_decimals, self.stars);
+        if !self.mods.is_empty() {
+            title.push_str(&format!(" +{}", self.mods.join("")));
+        }
+        if let Some(tag) = &self.tag {
+            title.push_str(&format!(" {}", tag));
+        }
+        title
     }
 
-    let title = format!("{} | {} - {} [{}] {:.2}⭐", username, mapset.artist, mapset.title, map.version, stars);
-    tracing::info!("Title has been generated successfully");
-    title
+    fn overflow(&self) -> usize {
+        self.render().chars().count().saturating_sub(TITLE_LIMIT)
+    }
+}
+
+/// Build `player | artist - title [difficulty] stars⭐ +mods tag` and shorten it
+/// until it fits into YouTube's title limit. Each step gives up a bit more,
+/// least useful information first, and the player and map title stay readable
+/// for as long as possible.
+pub fn fit_title(username: &str, artist: &str, title: &str, version: &str, stars: f32, mods: &[String], tag: Option<&str>) -> String {
+    let mut parts = TitleParts {
+        username: username.to_string(),
+        artist: Some(artist.to_string()),
+        title: title.to_string(),
+        version: version.to_string(),
+        stars,
+        star_decimals: 2,
+        mods: mods.to_vec(),
+        tag: tag.map(str::to_string),
+    };
+
+    let steps: [fn(&mut TitleParts); 5] = [
+        |parts| parts.star_decimals = 1,
+        |parts| parts.mods = compress_mods(&parts.mods),
+        |parts| parts.artist = None,
+        |parts| {
+            let length = parts.version.chars().count().saturating_sub(parts.overflow());
+            parts.version = shorten(&parts.version, length.max(MIN_NAME_LENGTH));
+        },
+        |parts| {
+            let length = parts.title.chars().count().saturating_sub(parts.overflow());
+            parts.title = shorten(&parts.title, length.max(MIN_NAME_LENGTH));
+        },
+    ];
+    for step in steps {
+        if parts.overflow() == 0 {
+            break;
+        }
+        step(&mut parts);
+    }
+
+    shorten(&parts.render(), TITLE_LIMIT)
+}
+
+/// Hard cut for titles that did not come out of `fit_title`.
+pub fn fit_to_limit(title: &str) -> String {
+    shorten(title, TITLE_LIMIT)
+}
+
+/// Drop mods that are implied by another one or that lazer puts on every
+/// stable-like score.
+fn compress_mods(mods: &[String]) -> Vec<String> {
+    // Custom rates are labelled like `DT(1.3x)`; only the acronym counts here.
+    let acronym = |game_mod: &str| game_mod.split('(').next().unwrap_or_default().to_string();
+    let has = |wanted: &str| mods.iter().any(|game_mod| acronym(game_mod) == wanted);
+    mods.iter()
+        .filter(|game_mod| match acronym(game_mod).as_str() {
+            "CL" => false,
+            "DT" => !has("NC"),
+            "HT" => !has("DC"),
+            "SD" => !has("PF"),
+            _ => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Cut `text` down to `length` characters, marking the cut with an ellipsis.
+fn shorten(text: &str, length: usize) -> String {
+    if text.chars().count() <= length {
+        return text.to_string();
+    }
+    let mut shortened: String = text.chars().take(length.saturating_sub(1)).collect();
+    shortened.truncate(shortened.trim_end().len());
+    shortened.push('…');
+    shortened
+}
+
+/// Tags on top of the fixed channel tags: the player, the mods (single and
+/// combined) and the star rating rounded down.
+pub fn generate_tags(username: &str, mods: &[String], stars: f32) -> Vec<String> {
+    let mut tags = vec![username.to_string()];
+    tags.extend(mods.iter().cloned());
+    if mods.len() > 1 {
+        tags.push(mods.join(""));
+    }
+    if stars > 0.0 {
+        tags.push(format!("{} star", stars.floor() as u32));
+    }
+    tags
 }
 
 pub fn generate_description(
@@ -45,7 +166,7 @@ pub fn generate_description(
     mapid: u32,
     score: Option<&rosu::Score>,
     time_string: Option<String>,
-    pp: Option<f32>,
+    rating: Option<&CalculateScoreResponse>,
     skin: Option<&OscWebSkin>,
 ) -> String {
     tracing::info!("Generating description");
@@ -68,8 +189,13 @@ pub fn generate_description(
         None => String::new(),
     };
 
+    let result_line = match rating {
+        Some(rating) => format!("\nResult: {:.0}pp, {}", rating.pp, rating.combo_annotation()),
+        None => String::new(),
+    };
+
     let mut tags = vec![format!("#osc_{}", userid)];
-    if let Some(pp_value) = pp {
+    if let Some(pp_value) = rating.map(|rating| rating.pp) {
         let bucket = (pp_value as i64 / 100) * 100;
         if bucket >= 100 {
             tags.push(format!("#osc_{}pp", bucket));
@@ -81,14 +207,123 @@ pub fn generate_description(
 "This score was set on {}.
 
 Player: https://osu.ppy.sh/users/{}
-Beatmap: https://osu.ppy.sh/beatmaps/{}
+Beatmap: https://osu.ppy.sh/beatmaps/{}{}
 {}{}
 
 Join the osu swiss community in discord: https://discord.com/invite/SHz8QtD
 
 {}",
-        timestamp, userid, mapid, score_link, skin_line, hashtags,
+        timestamp, userid, mapid, result_line, score_link, skin_line, hashtags,
     );
     tracing::info!("Description has been generated successfully");
     description
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mods(s: &[&str]) -> Vec<String> {
+        s.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn short_titles_are_untouched() {
+        assert_eq!(
+            fit_title("mrekk", "xi", "Blue Zenith", "FOUR DIMENSIONS", 7.2591, &mods(&["HD", "DT"]), None),
+            "mrekk | xi - Blue Zenith [FOUR DIMENSIONS] 7.26⭐ +HDDT"
+        );
+        assert_eq!(
+            fit_title("WhiteCat", "DragonForce", "Through the Fire and Flames", "Legend", 9.1, &[], None),
+            "WhiteCat | DragonForce - Through the Fire and Flames [Legend] 9.10⭐"
+        );
+    }
+
+    #[test]
+    fn tags_follow_the_mods() {
+        assert_eq!(
+            fit_title("mrekk", "xi", "Blue Zenith", "FOUR DIMENSIONS", 7.2591, &mods(&["HD", "DT"]), Some("FC")),
+            "mrekk | xi - Blue Zenith [FOUR DIMENSIONS] 7.26⭐ +HDDT FC"
+        );
+    }
+
+    #[test]
+    fn rounding_stars_and_compressing_mods_come_first() {
+        let title = fit_title(
+            "Lifeline",
+            "Camellia feat. Nanahira",
+            "Bassdrop Freaks (2018 Redrop ver.)",
+            "Nanahira's EX",
+            6.789,
+            &mods(&["HD", "DT", "NC", "CL"]),
+            None,
+        );
+        assert_eq!(title, "Lifeline | Camellia feat. Nanahira - Bassdrop Freaks (2018 Redrop ver.) [Nanahira's EX] 6.8⭐ +HDNC");
+        assert!(title.chars().count() <= TITLE_LIMIT);
+    }
+
+    #[test]
+    fn artist_goes_before_the_difficulty_is_cut() {
+        let title = fit_title(
+            "aetrna",
+            "Igorrr & Corpo-Mente",
+            "Viande (Corpo-Mente Remix) [Long Version]",
+            "Extra Hard ~Collab by Sotarks~",
+            7.01,
+            &mods(&["HD"]),
+            None,
+        );
+        assert_eq!(title, "aetrna | Viande (Corpo-Mente Remix) [Long Version] [Extra Hard ~Collab by Sotarks~] 7.0⭐ +HD");
+    }
+
+    #[test]
+    fn long_difficulty_names_are_shortened() {
+        let title = fit_title(
+            "Rafis",
+            "Hatsune Miku, Kagamine Rin, Megurine Luka",
+            "Rolling Girl (Cover by Wowaka's Friends)",
+            "Insane ~Guest Difficulty by a Mapper With a Rather Long Name and a Collab Partner~",
+            5.43,
+            &mods(&["HR"]),
+            None,
+        );
+        assert!(title.chars().count() <= TITLE_LIMIT);
+        assert!(title.starts_with("Rafis | Rolling Girl (Cover by Wowaka's Friends) [Insane"));
+        assert!(title.contains("…] 5.4⭐ +HR"));
+    }
+
+    #[test]
+    fn everything_oversized_still_fits_and_keeps_the_player() {
+        let title = fit_title(
+            "Mathi",
+            "Dark PHOENiX feat. The Spinning Mind Orchestra of Everlasting Long Artist Names",
+            "Kimi no Shiranai Monogatari -Bakemonogatari Original Soundtrack Extended Mix Director's Cut-",
+            "Ultimate Extreme Collab Difficulty by Everyone Who Ever Played This Game Over The Last Decade",
+            8.3333,
+            &mods(&["EZ", "HD", "DT", "FL", "SD"]),
+            None,
+        );
+        assert!(title.chars().count() <= TITLE_LIMIT);
+        assert!(title.starts_with("Mathi | Kimi no Shiranai"));
+        assert!(title.ends_with("8.3⭐ +EZHDDTFLSD"));
+    }
+
+    #[test]
+    fn compress_mods_drops_implied_mods() {
+        assert_eq!(compress_mods(&mods(&["HD", "DT", "NC", "PF", "SD", "CL"])), mods(&["HD", "NC", "PF"]));
+        assert_eq!(compress_mods(&mods(&["HT", "DC", "SD"])), mods(&["DC", "SD"]));
+    }
+
+    #[test]
+    fn compress_mods_reads_rate_labels() {
+        assert_eq!(compress_mods(&mods(&["HD", "DT(1.3x)", "NC(1.3x)", "CL"])), mods(&["HD", "NC(1.3x)"]));
+        assert_eq!(compress_mods(&mods(&["HT(0.8x)", "DC(0.8x)"])), mods(&["DC(0.8x)"]));
+    }
+
+    #[test]
+    fn shorten_marks_the_cut() {
+        assert_eq!(shorten("Extra Hard", 20), "Extra Hard");
+        assert_eq!(shorten("Extra Hard", 7), "Extra…");
+        assert_eq!(shorten("ねこみみスイッチ", 5), "ねこみみ…");
+    }
+}
@@ -94,6 +94,8 @@ async fn main() {
         .setup(|ctx, _ready, framework| {
             Box::pin(async move {
                 poise::builtins::register_globally(ctx.clone(), &framework.options().commands).await?;
+                apis::youtube::set_discord_http(ctx.http.clone());
+                apis::osu_link::start_listener(ctx.http.clone());
                 background_tasks::start_background_tasks(ctx);
                 tracing::info!("The bot is ready to use!");
                 Ok(Data {})
@@ -28,7 +28,32 @@ pub fn score_url(score_id: &u64) -> String {
 }
 
 pub fn mods_string(mods: &rosu::GameMods) -> String {
-    mods.iter().map(|map: &rosu::GameMod| map.acronym().to_string()).collect::<Vec<_>>().join("")
+    mod_labels(mods).join("")
+}
+
+/// Acronyms of `mods`, with the speed of rate mods the player changed from
+/// their default, e.g. `DT(1.3x)`.
+pub fn mod_labels(mods: &rosu::GameMods) -> Vec<String> {
+    mods.iter().map(|game_mod| match custom_rate(game_mod) {
+        Some(rate) => format!("{}({})", game_mod.acronym(), rate_string(rate)),
+        None => game_mod.acronym().to_string(),
+    }).collect()
+}
+
+/// Speed of DT/NC/HT/DC when it is not the mod's default.
+pub fn custom_rate(game_mod: &rosu::GameMod) -> Option<f64> {
+    let default = match game_mod.acronym().as_str() {
+        "DT" | "NC" => 1.5,
+        "HT" | "DC" => 0.75,
+        _ => return None,
+    };
+    game_mod.clock_rate().filter(|rate| (rate - default).abs() > 0.001)
+}
+
+/// `1.3x`, `1.25x`
+pub fn rate_string(rate: f64) -> String {
+    let rate = format!("{:.2}", rate);
+    format!("{}x", rate.trim_end_matches('0').trim_end_matches('.'))
 }
 
 pub fn convert_osu_db_to_mod_array(mods: osu_db::ModSet) -> Vec<String> {
@@ -0,0 +1,115 @@
+use rosu_v2::prelude as rosu;
+
+/// A score linked in a message. Legacy links carry the mode because their ids
+/// are only unique per mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreLink {
+    pub score_id: u64,
+    pub mode: Option<rosu::GameMode>,
+}
+
+/// A difficulty linked in a message, with the mode from the link if it had one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BeatmapLink {
+    pub map_id: u32,
+    pub mode: Option<rosu::GameMode>,
+}
+
+/// Every `osu.ppy.sh/scores/<id>` or `osu.ppy.sh/scores/<mode>/<id>` link in `text`.
+pub fn score_links(text: &str) -> Vec<ScoreLink> {
+    let mut links: Vec<ScoreLink> = vec![];
+    for url in osu_urls(text) {
+        let segments = path_segments(&url);
+        let link = match segments.as_slice() {
+            ["scores", id] => id.parse().ok().map(|score_id| ScoreLink { score_id, mode: None }),
+            ["scores", mode, id] => match (parse_mode(mode), id.parse()) {
+                (Some(mode), Ok(score_id)) => Some(ScoreLink { score_id, mode: Some(mode) }),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(link) = link && !links.contains(&link) {
+            links.push(link);
+        }
+    }
+    links
+}
+
+/// Every link to a single difficulty in `text`: `osu.ppy.sh/beatmapsets/<set>#<mode>/<id>`,
+/// `osu.ppy.sh/beatmaps/<id>` and the short `osu.ppy.sh/b/<id>`.
+pub fn beatmap_links(text: &str) -> Vec<BeatmapLink> {
+    let mut links: Vec<BeatmapLink> = vec![];
+    for url in osu_urls(text) {
+        let segments = path_segments(&url);
+        let link = match segments.as_slice() {
+            ["beatmapsets", _] | ["beatmapsets", _, "discussion", ..] => url.fragment()
+                .and_then(|fragment| fragment.split_once('/'))
+                .and_then(|(mode, id)| Some(BeatmapLink { map_id: id.parse().ok()?, mode: parse_mode(mode) })),
+            ["beatmaps" | "b", id] => id.parse().ok().map(|map_id| BeatmapLink {
+                map_id,
+                mode: url.query_pairs().find(|(key, _)| key == "mode").and_then(|(_, mode)| parse_mode(&mode)),
+            }),
+            _ => None,
+        };
+        if let Some(link) = link && !links.iter().any(|known| known.map_id == link.map_id) {
+            links.push(link);
+        }
+    }
+    links
+}
+
+/// The mode names used in osu! website paths.
+pub fn parse_mode(mode: &str) -> Option<rosu::GameMode> {
+    match mode {
+        "osu" => Some(rosu::GameMode::Osu),
+        "taiko" => Some(rosu::GameMode::Taiko),
+        "fruits" => Some(rosu::GameMode::Catch),
+        "mania" => Some(rosu::GameMode::Mania),
+        _ => None,
+    }
+}
+
+/// Every osu.ppy.sh link in `text`. Discord's `<link>` form that suppresses
+/// embeds is accepted too.
+fn osu_urls(text: &str) -> Vec<url::Url> {
+    text.split_whitespace()
+        .map(|word| word.trim_start_matches('<').trim_end_matches(['>', ')', ',', '.', '!', '?']))
+        .filter_map(|word| url::Url::parse(word).ok())
+        .filter(|url| url.host_str() == Some("osu.ppy.sh"))
+        .collect()
+}
+
+fn path_segments(url: &url::Url) -> Vec<&str> {
+    url.path_segments().map(|segments| segments.filter(|s| !s.is_empty()).collect()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_links_are_found_in_messages() {
+        let links = score_links("look at this https://osu.ppy.sh/scores/1234567890 and <https://osu.ppy.sh/scores/osu/4567>!");
+        assert_eq!(links, vec![
+            ScoreLink { score_id: 1234567890, mode: None },
+            ScoreLink { score_id: 4567, mode: Some(rosu::GameMode::Osu) },
+        ]);
+    }
+
+    #[test]
+    fn other_links_are_ignored() {
+        assert!(score_links("https://osu.ppy.sh/beatmapsets/1#osu/2 https://example.com/scores/1 osu.ppy.sh/scores/1").is_empty());
+        assert_eq!(score_links("https://osu.ppy.sh/scores/1 https://osu.ppy.sh/scores/1").len(), 1);
+    }
+
+    #[test]
+    fn beatmap_links_are_found_in_messages() {
+        let links = beatmap_links("https://osu.ppy.sh/beatmapsets/1#taiko/75 https://osu.ppy.sh/b/129891 <https://osu.ppy.sh/beatmaps/42?mode=mania>");
+        assert_eq!(links, vec![
+            BeatmapLink { map_id: 75, mode: Some(rosu::GameMode::Taiko) },
+            BeatmapLink { map_id: 129891, mode: None },
+            BeatmapLink { map_id: 42, mode: Some(rosu::GameMode::Mania) },
+        ]);
+        assert!(beatmap_links("https://osu.ppy.sh/beatmapsets/1 https://osu.ppy.sh/scores/1").is_empty());
+    }
+}
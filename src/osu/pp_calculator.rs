@@ -1,19 +1,78 @@
 use rosu_v2::prelude as rosu;
 use osu_db::Replay;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use rosu_pp::{Beatmap, Difficulty, Performance};
+use rosu_pp::any::DifficultyAttributes;
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 
 use crate::osu::formatter::convert_osu_db_to_mod_array;
 use crate::Error;
 
+/// Combo breaks up to this far below the map's max combo still count as a full
+/// combo; dropped slider ends cost combo without being a miss.
+const FULL_COMBO_TOLERANCE: u32 = 5;
+
 #[derive(Debug)]
 pub struct CalculateScoreResponse {
     pub accuracy: f32,
     pub pp: f32,
     pub star_rating: f32,
+    /// pp of the same play as a full combo: misses turned into 300s, max combo.
+    pub fc_pp: f32,
+    pub combo: u32,
+    pub max_combo: u32,
+    pub misses: u32,
+}
+
+impl CalculateScoreResponse {
+    pub fn is_full_combo(&self) -> bool {
+        self.misses == 0 && self.combo + FULL_COMBO_TOLERANCE >= self.max_combo
+    }
+
+    /// "FC", "1 miss" or "choke (X pp if FC)".
+    pub fn combo_annotation(&self) -> String {
+        if self.is_full_combo() {
+            "FC".to_string()
+        } else if self.misses == 1 {
+            format!("1 miss ({:.0}pp if FC)", self.fc_pp)
+        } else {
+            format!("choke ({:.0}pp if FC)", self.fc_pp)
+        }
+    }
+
+    /// Short form for video titles, only for plays worth pointing out.
+    pub fn title_tag(&self) -> Option<&'static str> {
+        if self.is_full_combo() {
+            Some("FC")
+        } else if self.misses == 1 {
+            Some("1 miss")
+        } else {
+            None
+        }
+    }
+}
+
+/// Accuracies the beatmap overview lists pp for.
+pub const MAP_ACCURACIES: [f64; 4] = [95.0, 98.0, 99.0, 100.0];
+
+/// Stars and pp at `MAP_ACCURACIES` for one mod combination.
+#[derive(Debug)]
+pub struct MapModsResponse {
+    pub mods: &'static str,
+    pub star_rating: f64,
+    pub pp: [f64; 4],
+}
+
+#[derive(Debug)]
+pub struct CalculateMapResponse {
+    pub ar: f32,
+    pub od: f32,
+    pub cs: f32,
+    pub hp: f32,
+    pub bpm: f64,
+    pub mods: Vec<MapModsResponse>,
 }
 
 // Helper function to get or download a beatmap file
@@ -50,6 +109,102 @@ async fn get_beatmap_file(map: &rosu::BeatmapExtended) -> Result<PathBuf, Error>
     Ok(beatmap_path)
 }
 
+/// What rosu-pp needs to know about a play's mods: the legacy bits plus the
+/// lazer settings they cannot express.
+#[derive(Debug, Clone, Default)]
+struct ModSettings {
+    bits: u32,
+    clock_rate: Option<f64>,
+    ar: Option<f32>,
+    od: Option<f32>,
+    cs: Option<f32>,
+    hp: Option<f32>,
+    /// Stable plays (classic mod or stable replays) use stable scoring.
+    lazer: bool,
+}
+
+impl ModSettings {
+    fn from_bits(bits: u32, lazer: bool) -> Self {
+        ModSettings { bits, lazer, ..Default::default() }
+    }
+
+    fn from_mods(mods: &rosu::GameMods) -> Self {
+        let mut settings = ModSettings {
+            bits: mods.bits(),
+            clock_rate: mods.clock_rate(),
+            lazer: !mods.iter().any(|game_mod| game_mod.acronym().as_str() == "CL"),
+            ..Default::default()
+        };
+        let as_f32 = |value: Option<f64>| value.map(|value| value as f32);
+        for game_mod in mods.iter() {
+            match game_mod {
+                rosu::GameMod::DifficultyAdjustOsu(da) => {
+                    settings.ar = as_f32(da.approach_rate);
+                    settings.od = as_f32(da.overall_difficulty);
+                    settings.cs = as_f32(da.circle_size);
+                    settings.hp = as_f32(da.drain_rate);
+                },
+                rosu::GameMod::DifficultyAdjustTaiko(da) => {
+                    settings.od = as_f32(da.overall_difficulty);
+                    settings.hp = as_f32(da.drain_rate);
+                },
+                rosu::GameMod::DifficultyAdjustCatch(da) => {
+                    settings.ar = as_f32(da.approach_rate);
+                    settings.od = as_f32(da.overall_difficulty);
+                    settings.cs = as_f32(da.circle_size);
+                    settings.hp = as_f32(da.drain_rate);
+                },
+                rosu::GameMod::DifficultyAdjustMania(da) => {
+                    settings.od = as_f32(da.overall_difficulty);
+                    settings.hp = as_f32(da.drain_rate);
+                },
+                _ => {},
+            }
+        }
+        settings
+    }
+
+    fn difficulty(&self) -> Difficulty {
+        let mut difficulty = Difficulty::new().mods(self.bits).lazer(self.lazer);
+        if let Some(clock_rate) = self.clock_rate {
+            difficulty = difficulty.clock_rate(clock_rate);
+        }
+        if let Some(ar) = self.ar {
+            difficulty = difficulty.ar(ar, false);
+        }
+        if let Some(od) = self.od {
+            difficulty = difficulty.od(od, false);
+        }
+        if let Some(cs) = self.cs {
+            difficulty = difficulty.cs(cs, false);
+        }
+        if let Some(hp) = self.hp {
+            difficulty = difficulty.hp(hp, false);
+        }
+        difficulty
+    }
+
+    fn performance<'map>(&self, diff_attrs: DifficultyAttributes) -> Performance<'map> {
+        let mut performance = Performance::new(diff_attrs).mods(self.bits).lazer(self.lazer);
+        if let Some(clock_rate) = self.clock_rate {
+            performance = performance.clock_rate(clock_rate);
+        }
+        if let Some(ar) = self.ar {
+            performance = performance.ar(ar, false);
+        }
+        if let Some(od) = self.od {
+            performance = performance.od(od, false);
+        }
+        if let Some(cs) = self.cs {
+            performance = performance.cs(cs, false);
+        }
+        if let Some(hp) = self.hp {
+            performance = performance.hp(hp, false);
+        }
+        performance
+    }
+}
+
 // Convert mod array to GameMods bitmask
 fn mods_to_bitmask(mods: &[String]) -> u32 {
     let mut bits = 0u32;
@@ -63,103 +218,301 @@ fn mods_to_bitmask(mods: &[String]) -> u32 {
             "SD" => 32,
             "DT" | "NC" => 64,
             "RX" => 128,
-            "HT" => 256,
+            "HT" | "DC" => 256,
             "FL" => 1024,
             "SO" => 4096,
+            // Key count mods change how osu!standard maps convert to mania
+            "4K" => 1 << 15,
+            "5K" => 1 << 16,
+            "6K" => 1 << 17,
+            "7K" => 1 << 18,
+            "8K" => 1 << 19,
+            "9K" => 1 << 24,
+            "1K" => 1 << 26,
+            "3K" => 1 << 27,
+            "2K" => 1 << 28,
             _ => 0,
         };
     }
     bits
 }
 
-// Calculate accuracy from hit counts
-fn calculate_accuracy(count_300: u32, count_100: u32, count_50: u32, count_miss: u32) -> f32 {
-    let total_hits = count_300 + count_100 + count_50 + count_miss;
-    if total_hits == 0 {
-        return 100.0;
+/// Hit counts in the shape rosu-pp expects for the score's mode: gekis are
+/// mania perfects, katus are mania goods or catch tiny droplet misses, and
+/// catch puts droplets into n100 and tiny droplets into n50.
+#[derive(Debug, Clone, Copy)]
+struct Hits {
+    mode: rosu::GameMode,
+    n_geki: u32,
+    n300: u32,
+    n_katu: u32,
+    n100: u32,
+    n50: u32,
+    misses: u32,
+    /// The part of catch's `misses` that were droplets. Stable replays only
+    /// store the sum, so it stays 0 for them.
+    droplet_misses: u32,
+    /// Hit slider ticks and repeats of lazer osu!standard scores. Left unset,
+    /// rosu-pp counts all of them as hit.
+    large_tick_hits: Option<u32>,
+    /// Hit slider ends of lazer osu!standard scores, unset like `large_tick_hits`.
+    slider_end_hits: Option<u32>,
+    combo: u32,
+}
+
+impl Hits {
+    fn from_score(score: &rosu::Score, lazer: bool) -> Self {
+        Self::from_statistics(score.mode, &score.statistics, score.max_combo, lazer)
+    }
+
+    fn from_statistics(mode: rosu::GameMode, statistics: &rosu::ScoreStatistics, combo: u32, lazer: bool) -> Self {
+        let mut hits = Hits {
+            mode,
+            n_geki: 0,
+            n300: statistics.great,
+            n_katu: 0,
+            n100: statistics.ok,
+            n50: statistics.meh,
+            misses: statistics.miss,
+            droplet_misses: 0,
+            large_tick_hits: None,
+            slider_end_hits: None,
+            combo,
+        };
+        match mode {
+            // Stable scoring (and classic mod) doesn't judge slider ends.
+            rosu::GameMode::Osu if lazer => {
+                hits.large_tick_hits = Some(statistics.large_tick_hit);
+                hits.slider_end_hits = Some(statistics.slider_tail_hit);
+            },
+            rosu::GameMode::Osu | rosu::GameMode::Taiko => {},
+            rosu::GameMode::Catch => {
+                hits.n100 = statistics.large_tick_hit;
+                hits.n50 = statistics.small_tick_hit;
+                hits.n_katu = statistics.small_tick_miss;
+                hits.misses = statistics.miss + statistics.large_tick_miss;
+                hits.droplet_misses = statistics.large_tick_miss;
+            },
+            rosu::GameMode::Mania => {
+                hits.n_geki = statistics.perfect;
+                hits.n_katu = statistics.good;
+            },
+        }
+        hits
+    }
+
+    /// Stable replays already store the counts the way rosu-pp wants them.
+    fn from_replay(replay: &Replay) -> Self {
+        Hits {
+            mode: rosu::GameMode::from(replay.mode.raw()),
+            n_geki: replay.count_geki as u32,
+            n300: replay.count_300 as u32,
+            n_katu: replay.count_katsu as u32,
+            n100: replay.count_100 as u32,
+            n50: replay.count_50 as u32,
+            misses: replay.count_miss as u32,
+            droplet_misses: 0,
+            large_tick_hits: None,
+            slider_end_hits: None,
+            combo: replay.max_combo as u32,
+        }
+    }
+
+    /// The same play without misses, every miss counted as the best judgement.
+    /// Missed droplets in catch become caught droplets, not fruits, and every
+    /// slider tick and end counts as hit.
+    fn full_combo(&self, max_combo: u32) -> Self {
+        let mut hits = *self;
+        match self.mode {
+            rosu::GameMode::Mania => hits.n_geki += self.misses,
+            rosu::GameMode::Catch => {
+                hits.n300 += self.misses - self.droplet_misses;
+                hits.n100 += self.droplet_misses;
+            },
+            _ => hits.n300 += self.misses,
+        }
+        hits.misses = 0;
+        hits.droplet_misses = 0;
+        hits.large_tick_hits = None;
+        hits.slider_end_hits = None;
+        hits.combo = max_combo;
+        hits
+    }
+
+    fn apply<'map>(&self, mut performance: Performance<'map>) -> Performance<'map> {
+        if let Some(large_tick_hits) = self.large_tick_hits {
+            performance = performance.large_tick_hits(large_tick_hits);
+        }
+        if let Some(slider_end_hits) = self.slider_end_hits {
+            performance = performance.slider_end_hits(slider_end_hits);
+        }
+        performance
+            .combo(self.combo)
+            .n_geki(self.n_geki)
+            .n300(self.n300)
+            .n_katu(self.n_katu)
+            .n100(self.n100)
+            .n50(self.n50)
+            .misses(self.misses)
+    }
+
+    /// Accuracy in percent, following each mode's own formula.
+    fn accuracy(&self) -> f32 {
+        let (achieved, possible) = match self.mode {
+            rosu::GameMode::Osu => (
+                300 * self.n300 + 100 * self.n100 + 50 * self.n50,
+                300 * (self.n300 + self.n100 + self.n50 + self.misses),
+            ),
+            rosu::GameMode::Taiko => (
+                2 * self.n300 + self.n100,
+                2 * (self.n300 + self.n100 + self.misses),
+            ),
+            rosu::GameMode::Catch => (
+                self.n300 + self.n100 + self.n50,
+                self.n300 + self.n100 + self.n50 + self.n_katu + self.misses,
+            ),
+            rosu::GameMode::Mania => (
+                300 * (self.n_geki + self.n300) + 200 * self.n_katu + 100 * self.n100 + 50 * self.n50,
+                300 * (self.n_geki + self.n300 + self.n_katu + self.n100 + self.n50 + self.misses),
+            ),
+        };
+        if possible == 0 {
+            return 100.0;
+        }
+        achieved as f32 / possible as f32 * 100.0
     }
-    let weighted_hits = (count_300 * 300 + count_100 * 100 + count_50 * 50) as f32;
-    let max_hits = (total_hits * 300) as f32;
-    (weighted_hits / max_hits) * 100.0
 }
 
-pub async fn calculate_score_by_score(score: &rosu::Score) -> Result<CalculateScoreResponse, Error> {
-    // Get beatmap from API
-    let map = crate::osu::get_osu_instance()
-        .beatmap()
-        .map_id(score.map_id)
-        .await?;
-    
-    let beatmap_path = get_beatmap_file(&map).await?;
-    let beatmap = Beatmap::from_path(&beatmap_path)
+/// Convert the cached beatmap into `mode` (only osu!standard maps convert) and
+/// rate `hits` on it.
+fn calculate_hits(beatmap_path: &Path, mode: rosu::GameMode, mods: &ModSettings, hits: Hits, accuracy: f32) -> Result<CalculateScoreResponse, Error> {
+    let mut beatmap = Beatmap::from_path(beatmap_path)
         .map_err(|e| anyhow::anyhow!("Failed to parse beatmap: {:?}", e))?;
-    
-    let mods: Vec<String> = score.mods.iter().map(|m| m.acronym().to_string()).collect();
-    let mod_bits = mods_to_bitmask(&mods);
+    beatmap.convert_mut(rosu_pp::model::mode::GameMode::from(mode as u8), &mods.bits.into())
+        .map_err(|e| anyhow::anyhow!("Failed to convert beatmap: {:?}", e))?;
     
     // Calculate difficulty locally using rosu-pp
-    let diff_attrs = Difficulty::new()
-        .mods(mod_bits)
-        .calculate(&beatmap);
+    let diff_attrs = mods.difficulty().calculate(&beatmap);
     
     let stars = diff_attrs.stars();
+    let max_combo = diff_attrs.max_combo();
     
     // Calculate performance locally using rosu-pp
-    let perf_attrs = Performance::new(diff_attrs)
-        .mods(mod_bits)
-        .combo(score.max_combo)
-        .n300(score.statistics.great)
-        .n100(score.statistics.ok)
-        .n50(score.statistics.meh)
-        .misses(score.statistics.miss)
-        .calculate();
-    
-    let pp = perf_attrs.pp() as f32;
-    let accuracy = score.accuracy;
+    let perf_attrs = hits.apply(mods.performance(diff_attrs.clone())).calculate();
+    let fc_attrs = hits.full_combo(max_combo).apply(mods.performance(diff_attrs)).calculate();
     
     Ok(CalculateScoreResponse {
         accuracy,
-        pp,
+        pp: perf_attrs.pp() as f32,
         star_rating: stars as f32,
+        fc_pp: fc_attrs.pp() as f32,
+        combo: hits.combo,
+        max_combo,
+        misses: hits.misses,
     })
 }
 
+pub async fn calculate_score_by_score(score: &rosu::Score) -> Result<CalculateScoreResponse, Error> {
+    // Get beatmap from API
+    let map = crate::osu::get_osu_instance()
+        .beatmap()
+        .map_id(score.map_id)
+        .await?;
+    
+    let beatmap_path = get_beatmap_file(&map).await?;
+    
+    let mods = ModSettings::from_mods(&score.mods);
+    let hits = Hits::from_score(score, mods.lazer);
+    calculate_hits(&beatmap_path, score.mode, &mods, hits, score.accuracy)
+}
+
 pub async fn calculate_score_by_replay(replay: &Replay, map: &rosu::BeatmapExtended) -> Result<CalculateScoreResponse, Error> {
     let beatmap_path = get_beatmap_file(map).await?;
-    let beatmap = Beatmap::from_path(&beatmap_path)
-        .map_err(|e| anyhow::anyhow!("Failed to parse beatmap: {:?}", e))?;
-    
     let mods = convert_osu_db_to_mod_array(replay.mods);
-    let mod_bits = mods_to_bitmask(&mods);
+    // .osr files only come from stable
+    let mod_settings = ModSettings::from_bits(mods_to_bitmask(&mods), false);
+    let hits = Hits::from_replay(replay);
     
-    // Calculate difficulty locally using rosu-pp
-    let diff_attrs = Difficulty::new()
-        .mods(mod_bits)
-        .calculate(&beatmap);
-    
-    let stars = diff_attrs.stars();
-    
-    // Calculate performance locally using rosu-pp
-    let perf_attrs = Performance::new(diff_attrs)
-        .mods(mod_bits)
-        .combo(replay.max_combo as u32)
-        .n300(replay.count_300 as u32)
-        .n100(replay.count_100 as u32)
-        .n50(replay.count_50 as u32)
-        .misses(replay.count_miss as u32)
-        .calculate();
-    
-    let pp = perf_attrs.pp() as f32;
-    let accuracy = calculate_accuracy(
-        replay.count_300 as u32,
-        replay.count_100 as u32,
-        replay.count_50 as u32,
-        replay.count_miss as u32,
-    );
-    
-    Ok(CalculateScoreResponse {
-        accuracy,
-        pp,
-        star_rating: stars as f32,
+    calculate_hits(&beatmap_path, hits.mode, &mod_settings, hits, hits.accuracy())
+}
+
+/// Difficulty attributes and pp for a beatmap under NM/HD/HR/DT. `mode` converts
+/// osu!standard maps to another mode.
+pub async fn calculate_map(map: &rosu::BeatmapExtended, mode: rosu::GameMode) -> Result<CalculateMapResponse, Error> {
+    let beatmap_path = get_beatmap_file(map).await?;
+    let mut beatmap = Beatmap::from_path(&beatmap_path)
+        .map_err(|e| anyhow::anyhow!("Failed to parse beatmap: {:?}", e))?;
+    beatmap.convert_mut(rosu_pp::model::mode::GameMode::from(mode as u8), &0.into())
+        .map_err(|e| anyhow::anyhow!("Failed to convert beatmap: {:?}", e))?;
+
+    let attributes = beatmap.attributes().build();
+    let mods = ["NM", "HD", "HR", "DT"].into_iter().map(|name| {
+        let mod_bits = mods_to_bitmask(&[name.to_string()]);
+        let diff_attrs = Difficulty::new()
+            .mods(mod_bits)
+            .calculate(&beatmap);
+        let star_rating = diff_attrs.stars();
+        let pp = MAP_ACCURACIES.map(|accuracy| Performance::new(diff_attrs.clone())
+            .mods(mod_bits)
+            .accuracy(accuracy)
+            .calculate()
+            .pp());
+        MapModsResponse { mods: name, star_rating, pp }
+    }).collect();
+
+    Ok(CalculateMapResponse {
+        ar: attributes.ar(),
+        od: attributes.od(),
+        cs: attributes.cs(),
+        hp: attributes.hp(),
+        bpm: beatmap.bpm(),
+        mods,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Four two-beat sliders, each with a head, one tick and an end.
+    const SLIDER_MAP: &str = "osu file format v14
+
+[General]
+Mode: 0
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:8
+ApproachRate:9
+SliderMultiplier:1.4
+SliderTickRate:1
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+100,100,1000,2,0,L|380:100,1,280
+100,200,3000,2,0,L|380:200,1,280
+100,300,5000,2,0,L|380:300,1,280
+100,100,7000,2,0,L|380:100,1,280
+";
+
+    #[test]
+    fn lazer_slider_end_misses_lower_pp() {
+        let path = std::env::temp_dir().join("oscbot_pp_calculator_sliders.osu");
+        std::fs::write(&path, SLIDER_MAP).unwrap();
+        let mods = ModSettings::from_bits(0, true);
+        let statistics = |slider_tail_hit| rosu::ScoreStatistics { great: 4, large_tick_hit: 4, slider_tail_hit, ..Default::default() };
+
+        let all_hit = Hits::from_statistics(rosu::GameMode::Osu, &statistics(4), 12, true);
+        let ends_missed = Hits::from_statistics(rosu::GameMode::Osu, &statistics(2), 6, true);
+        let all_hit = calculate_hits(&path, rosu::GameMode::Osu, &mods, all_hit, 100.0).unwrap();
+        let ends_missed = calculate_hits(&path, rosu::GameMode::Osu, &mods, ends_missed, 100.0).unwrap();
+
+        assert_eq!(all_hit.max_combo, 12);
+        assert!(ends_missed.pp < all_hit.pp);
+        // Without the missed ends the play is the same as the one that hit them all.
+        assert!((ends_missed.fc_pp - all_hit.pp).abs() < 0.001);
+    }
+}